@@ -0,0 +1,483 @@
+//! Compile-time evaluation of `const` declarations.
+//!
+//! A constant initializer may only use literals, arithmetic and other
+//! constants, including ones imported from other modules.
+
+use std::{collections::HashMap, fmt};
+
+use super::numeric::NumType;
+use crate::{
+    error::Diagnostic,
+    parser::ast::{BinaryOpKind, CatchClause, Expr, Ident, IfBranch, Module, Span, Stmt},
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ConstValue {
+    Integer(i64),
+    Float(f32),
+    String(String),
+}
+
+impl fmt::Display for ConstValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstValue::Integer(i) => write!(f, "{}", i),
+            ConstValue::Float(n) => write!(f, "{:?}", n),
+            ConstValue::String(s) => write!(f, "\"{}\"", s),
+        }
+    }
+}
+
+/// Values of constants by their name
+pub type Consts = HashMap<String, ConstValue>;
+
+/// Constants of imported modules by the path they are imported from
+pub type ImportedConsts = HashMap<String, Consts>;
+
+/// Evaluates every constant of the module.
+///
+/// Returns the top-level constants of the module, which other modules
+/// may import, and errors for initializers that can't be evaluated.
+pub fn eval_consts(module: &Module, imported: &ImportedConsts) -> (Consts, Vec<Diagnostic>) {
+    let mut evaluator = Evaluator {
+        scopes: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+
+    let mut top_level = HashMap::new();
+    for stmt in &module.statements {
+        if let Stmt::Import {
+            symbols,
+            namespace,
+            path,
+            ..
+        } = stmt
+        {
+            if let Some(namespace) = namespace {
+                top_level.insert(namespace.name.clone(), Binding::Runtime);
+            }
+            for sym in symbols {
+                let local = sym.imported_as.as_ref().unwrap_or(&sym.name);
+                let value = imported.get(path).and_then(|c| c.get(&sym.name));
+                top_level.insert(
+                    local.clone(),
+                    value.cloned().map_or(Binding::Runtime, Binding::Const),
+                );
+            }
+        }
+    }
+    evaluator.scopes.push(top_level);
+    evaluator.eval_body(&module.statements);
+
+    let exports = module
+        .statements
+        .iter()
+        .flat_map(|stmt| match stmt {
+            Stmt::Const { name, .. } => vec![&name.name],
+            // Re-exported constants keep their value
+            Stmt::Import {
+                symbols,
+                is_pub: true,
+                ..
+            } => symbols
+                .iter()
+                .map(|sym| sym.imported_as.as_ref().unwrap_or(&sym.name))
+                .collect(),
+            _ => Vec::new(),
+        })
+        .filter_map(|name| {
+            let Some(Binding::Const(value)) = evaluator.scopes[0].get(name) else {
+                return None;
+            };
+            Some((name.clone(), value.clone()))
+        })
+        .collect();
+    (exports, evaluator.diagnostics)
+}
+
+struct Evaluator {
+    scopes: Vec<HashMap<String, Binding>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// What a name in scope refers to
+enum Binding {
+    Const(ConstValue),
+    /// Anything which isn't a constant
+    Runtime,
+    /// Constant whose initializer has a syntax error
+    Invalid,
+}
+
+enum EvalError {
+    /// Reason why the expression isn't a constant, reported at the span
+    /// when there is one and at the constant's name otherwise
+    NotConstant(String, Option<Span>),
+    /// The expression has a syntax error, which was already reported by the parser
+    Invalid,
+}
+
+impl Evaluator {
+    fn declare(&mut self, name: &Ident, value: Binding) {
+        self.scopes
+            .last_mut()
+            .expect("bindings are declared inside a scope")
+            .insert(name.name.clone(), value);
+    }
+
+    fn eval_block(&mut self, body: &[Stmt]) {
+        self.scopes.push(HashMap::new());
+        self.eval_body(body);
+        self.scopes.pop();
+    }
+
+    fn eval_body(&mut self, body: &[Stmt]) {
+        for stmt in body {
+            if let Stmt::Function { name, .. } = stmt {
+                self.declare(name, Binding::Runtime);
+            }
+        }
+        for stmt in body {
+            self.eval_stmt(stmt);
+        }
+    }
+
+    fn eval_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Const { name, value } => {
+                let value = match self.eval(value) {
+                    Ok(value) => Binding::Const(value),
+                    Err(EvalError::NotConstant(reason, at)) => {
+                        let span = at.unwrap_or(name.span);
+                        self.diagnostics.push(Diagnostic::error(
+                            format!(
+                                "constant `{}` can't be evaluated at compile time: {}",
+                                name.name, reason
+                            ),
+                            span,
+                        ));
+                        Binding::Runtime
+                    }
+                    Err(EvalError::Invalid) => Binding::Invalid,
+                };
+                self.declare(name, value);
+            }
+            Stmt::Var { pattern, value, .. } => {
+                self.eval_nested(value);
+                for name in pattern.bindings() {
+                    self.declare(name, Binding::Runtime);
+                }
+            }
+            Stmt::ExternFunction { name, .. } => self.declare(name, Binding::Runtime),
+            Stmt::Function { params, body, .. } => {
+                self.scopes.push(HashMap::new());
+                for param in params {
+                    self.declare(&param.name, Binding::Runtime);
+                }
+                self.eval_block(&body.statements);
+                self.scopes.pop();
+            }
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.eval_block(&body.statements);
+                if let Some(CatchClause { binding, body }) = catch {
+                    self.scopes.push(HashMap::new());
+                    if let Some(binding) = binding {
+                        self.declare(binding, Binding::Runtime);
+                    }
+                    self.eval_block(&body.statements);
+                    self.scopes.pop();
+                }
+                if let Some(finally) = finally {
+                    self.eval_block(&finally.statements);
+                }
+            }
+            Stmt::Throw { value, .. } | Stmt::Expr(value) => self.eval_nested(value),
+            Stmt::Import { .. } => {}
+        }
+    }
+
+    // Evaluates the constants declared in the bodies of `if` expressions
+    fn eval_nested(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Integer(_)
+            | Expr::Float(_)
+            | Expr::String(_)
+            | Expr::Ident(_)
+            | Expr::Error(_) => {}
+            Expr::BinaryOp { left, right, .. } => {
+                self.eval_nested(left);
+                self.eval_nested(right);
+            }
+            Expr::Call { target, arguments } => {
+                self.eval_nested(target);
+                for arg in arguments {
+                    self.eval_nested(&arg.expr);
+                }
+            }
+            Expr::BracketAccess { target, expr } => {
+                self.eval_nested(target);
+                self.eval_nested(expr);
+            }
+            Expr::DotAccess { target: expr, .. } | Expr::Await(expr) | Expr::Cast { expr, .. } => {
+                self.eval_nested(expr)
+            }
+            Expr::If {
+                branches,
+                else_body,
+            } => {
+                for IfBranch { condition, body } in branches {
+                    self.eval_nested(condition);
+                    self.eval_block(&body.statements);
+                }
+                if let Some(body) = else_body {
+                    self.eval_block(&body.statements);
+                }
+            }
+        }
+    }
+
+    fn lookup(&self, name: &Ident) -> Result<ConstValue, EvalError> {
+        match self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.name))
+        {
+            Some(Binding::Const(value)) => Ok(value.clone()),
+            Some(Binding::Invalid) => Err(EvalError::Invalid),
+            _ => Err(EvalError::NotConstant(
+                format!("`{}` is not a constant", name.name),
+                Some(name.span),
+            )),
+        }
+    }
+
+    fn eval(&self, expr: &Expr) -> Result<ConstValue, EvalError> {
+        match expr {
+            Expr::Integer(i) => Ok(ConstValue::Integer(i64::from(*i))),
+            Expr::Float(f) => Ok(ConstValue::Float(*f)),
+            Expr::String(s) => Ok(ConstValue::String(s.clone())),
+            Expr::Ident(name) => self.lookup(name),
+            Expr::BinaryOp { kind, left, right } => {
+                let left = self.eval(left)?;
+                let right = self.eval(right)?;
+                fold(kind, left, right).map_err(|reason| EvalError::NotConstant(reason, None))
+            }
+            Expr::Call { .. } => Err(EvalError::NotConstant(
+                "function calls are evaluated at run time".to_string(),
+                None,
+            )),
+            Expr::DotAccess { .. } | Expr::BracketAccess { .. } => Err(EvalError::NotConstant(
+                "member access is evaluated at run time".to_string(),
+                None,
+            )),
+            Expr::Await(_) => Err(EvalError::NotConstant(
+                "`await` is evaluated at run time".to_string(),
+                None,
+            )),
+            Expr::If { .. } => Err(EvalError::NotConstant(
+                "`if` is evaluated at run time".to_string(),
+                None,
+            )),
+            Expr::Error(_) => Err(EvalError::Invalid),
+            Expr::Cast { expr, r#type } => {
+                let value = self.eval(expr)?;
+                let Some(to) = NumType::from_usage(r#type) else {
+                    return Err(EvalError::NotConstant(
+                        format!("`{}` is not a numeric type", r#type.dump()),
+                        None,
+                    ));
+                };
+                cast(value, to).map_err(|reason| EvalError::NotConstant(reason, None))
+            }
+        }
+    }
+}
+
+// Converts like the code `as` compiles to: 32-bit integers wrap around
+// like `| 0` and `>>> 0`, 64-bit integers only truncate like `Math.trunc`
+fn cast(value: ConstValue, to: NumType) -> Result<ConstValue, String> {
+    match value {
+        ConstValue::Integer(i) if to.is_float() => Ok(ConstValue::Float(i as f32)),
+        ConstValue::Integer(i) => Ok(ConstValue::Integer(match to {
+            NumType::I32 => i64::from(i as i32),
+            NumType::U32 => i64::from(i as u32),
+            _ => i,
+        })),
+        ConstValue::Float(f) if to.is_float() => Ok(ConstValue::Float(f)),
+        // Like `ToInt32`, which keeps the lower 32 bits of the truncated value
+        ConstValue::Float(f) if matches!(to, NumType::I32 | NumType::U32) => {
+            let bits = if f.is_finite() {
+                f64::from(f).trunc().rem_euclid(4294967296.0) as i64
+            } else {
+                0
+            };
+            cast(ConstValue::Integer(bits), to)
+        }
+        ConstValue::Float(f) if f.is_finite() => Ok(ConstValue::Integer(f.trunc() as i64)),
+        value => Err(format!("{} can't be cast to `{}`", value, to)),
+    }
+}
+
+fn fold(kind: &BinaryOpKind, left: ConstValue, right: ConstValue) -> Result<ConstValue, String> {
+    use ConstValue::*;
+
+    match (left, right) {
+        (Integer(l), Integer(r)) => {
+            let result = match kind {
+                BinaryOpKind::Add => l.checked_add(r),
+                BinaryOpKind::Sub => l.checked_sub(r),
+                BinaryOpKind::Mul => l.checked_mul(r),
+                BinaryOpKind::Div if r == 0 => return Err("division by zero".to_string()),
+                // Truncates towards zero like the division compiled for integers
+                BinaryOpKind::Div => l.checked_div(r),
+                BinaryOpKind::Rem if r == 0 => return Err("division by zero".to_string()),
+                BinaryOpKind::Rem => l.checked_rem(r),
+            };
+            result
+                .map(Integer)
+                .ok_or_else(|| "integer overflow".to_string())
+        }
+        (Integer(l), Float(r)) => fold(kind, Float(l as f32), Float(r)),
+        (Float(l), Integer(r)) => fold(kind, Float(l), Float(r as f32)),
+        (Float(l), Float(r)) => Ok(Float(match kind {
+            BinaryOpKind::Add => l + r,
+            BinaryOpKind::Sub => l - r,
+            BinaryOpKind::Mul => l * r,
+            BinaryOpKind::Div => l / r,
+            BinaryOpKind::Rem => l % r,
+        })),
+        (String(l), String(r)) if *kind == BinaryOpKind::Add => Ok(String(l + &r)),
+        (l, r) => Err(format!(
+            "`{}` can't be applied to {} and {}",
+            kind.to_op(),
+            l,
+            r
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::parser::{
+        ast::{Expr, Span, Stmt},
+        parse,
+    };
+
+    use super::{eval_consts, ConstValue, Consts, ImportedConsts};
+
+    fn eval(source: &str, imported: &ImportedConsts) -> (Consts, Vec<String>) {
+        let (consts, diagnostics) = eval_consts(&parse(source).unwrap(), imported);
+        let errors = diagnostics.into_iter().map(|d| d.render(source)).collect();
+        (consts, errors)
+    }
+
+    #[test]
+    fn folds_constant_expressions() {
+        let (consts, errors) = eval(
+            "const A = 2 * (3 + 4)\nconst B = A % 5 + 0.5\nconst C = \"a\" + \"b\"\nconst D = B as i32 * 2\nlet d = 1",
+            &HashMap::new(),
+        );
+        assert!(errors.is_empty());
+        assert_eq!(
+            consts,
+            HashMap::from([
+                ("A".to_string(), ConstValue::Integer(14)),
+                ("B".to_string(), ConstValue::Float(4.5)),
+                ("C".to_string(), ConstValue::String("ab".to_string())),
+                ("D".to_string(), ConstValue::Integer(8)),
+            ])
+        );
+    }
+
+    #[test]
+    fn casts_and_division_match_the_compiled_code() {
+        let (consts, errors) = eval(
+            "const A = 3000000000.0 as i32\nconst B = (0 - 1) as u32\nconst C = (0 - 2.5) as u32\nconst D = 2.9 as i64\nconst E = 7 / 2\nconst F = 7.0 / 2",
+            &HashMap::new(),
+        );
+        assert!(errors.is_empty());
+        assert_eq!(
+            consts,
+            HashMap::from([
+                ("A".to_string(), ConstValue::Integer(-1294967296)),
+                ("B".to_string(), ConstValue::Integer(4294967295)),
+                ("C".to_string(), ConstValue::Integer(4294967294)),
+                ("D".to_string(), ConstValue::Integer(2)),
+                ("E".to_string(), ConstValue::Integer(3)),
+                ("F".to_string(), ConstValue::Float(3.5)),
+            ])
+        );
+    }
+
+    #[test]
+    fn uses_imported_constants() {
+        let imported = HashMap::from([(
+            "./sizes.sk".to_string(),
+            HashMap::from([("SIZE".to_string(), ConstValue::Integer(8))]),
+        )]);
+        let (consts, errors) = eval(
+            "import { SIZE as S, other } from \"./sizes.sk\"\nconst DOUBLE = S * 2\nconst BAD = other",
+            &imported,
+        );
+        assert_eq!(
+            consts,
+            HashMap::from([("DOUBLE".to_string(), ConstValue::Integer(16))])
+        );
+        assert_eq!(
+            errors,
+            vec!["error at 3:13: constant `BAD` can't be evaluated at compile time: `other` is not a constant"]
+        );
+    }
+
+    #[test]
+    fn reexports_imported_constants() {
+        let imported = HashMap::from([(
+            "./sizes.sk".to_string(),
+            HashMap::from([("SIZE".to_string(), ConstValue::Integer(8))]),
+        )]);
+        let (consts, errors) = eval("pub import { SIZE as S } from \"./sizes.sk\"", &imported);
+        assert_eq!(
+            consts,
+            HashMap::from([("S".to_string(), ConstValue::Integer(8))])
+        );
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn rejects_runtime_constructs() {
+        let (_, errors) = eval(
+            "let a = 1\nconst A = a\nconst B = f(1)\nconst C = 1 / 0\nconst D = \"a\" * 2\nfn f(x: int): int {\n    const E = x + 1\n    if x { const F = x } else { 1 }\n}",
+            &HashMap::new(),
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "error at 2:11: constant `A` can't be evaluated at compile time: `a` is not a constant",
+                "error at 3:7: constant `B` can't be evaluated at compile time: function calls are evaluated at run time",
+                "error at 4:7: constant `C` can't be evaluated at compile time: division by zero",
+                "error at 5:7: constant `D` can't be evaluated at compile time: `*` can't be applied to \"a\" and 2",
+                "error at 7:15: constant `E` can't be evaluated at compile time: `x` is not a constant",
+                "error at 8:22: constant `F` can't be evaluated at compile time: `x` is not a constant",
+            ]
+        );
+    }
+
+    #[test]
+    fn syntax_errors_are_not_reported_again() {
+        let mut module = parse("const A = 1\nconst B = A + 1\nconst C = B * 2").unwrap();
+        let Stmt::Const { value, .. } = &mut module.statements[0] else {
+            panic!("the first statement should be a constant");
+        };
+        *value = Expr::Error(Span::new(10, 11));
+        let (consts, errors) = eval_consts(&module, &HashMap::new());
+        assert!(consts.is_empty());
+        assert!(errors.is_empty());
+    }
+}
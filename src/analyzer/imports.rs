@@ -0,0 +1,204 @@
+//! Names imported from other modules which those modules don't export.
+
+use std::collections::HashMap;
+
+use crate::{
+    analyzer::{
+        suggest::similar_names,
+        symbols::{SymbolId, SymbolTable},
+    },
+    error::Diagnostic,
+    parser::ast::{CatchClause, Expr, Ident, IfBranch, Module, Span, Stmt},
+};
+
+/// Exported names of imported modules by the path they are imported from.
+/// Modules which couldn't be read are missing and not checked.
+pub type ImportedExports = HashMap<String, Vec<String>>;
+
+/// Names other modules can import from the module: its top-level
+/// variables, constants and functions, and everything imported with
+/// `pub import`
+pub fn exports(module: &Module) -> Vec<String> {
+    let mut names = Vec::new();
+    for stmt in &module.statements {
+        match stmt {
+            Stmt::Var { pattern, .. } => {
+                names.extend(pattern.bindings().into_iter().map(|b| b.name.clone()))
+            }
+            Stmt::Const { name, .. } | Stmt::Function { name, .. } => names.push(name.name.clone()),
+            Stmt::Import {
+                symbols,
+                namespace,
+                is_pub: true,
+                ..
+            } => {
+                names.extend(
+                    symbols
+                        .iter()
+                        .map(|sym| sym.imported_as.as_ref().unwrap_or(&sym.name).clone()),
+                );
+                names.extend(namespace.iter().map(|n| n.name.clone()));
+            }
+            _ => {}
+        }
+    }
+    names
+}
+
+pub fn check(module: &Module, imported: &ImportedExports) -> Vec<Diagnostic> {
+    let table = SymbolTable::build(module);
+    let mut namespaces: Vec<(SymbolId, &[String], &str)> = Vec::new();
+    let mut diagnostics = Vec::new();
+    for stmt in &module.statements {
+        let Stmt::Import {
+            symbols,
+            namespace,
+            path,
+            ..
+        } = stmt
+        else {
+            continue;
+        };
+        let Some(exports) = imported.get(path) else {
+            continue;
+        };
+        for sym in symbols {
+            if !exports.contains(&sym.name) {
+                diagnostics.push(unknown_export(path, &sym.name, exports, sym.span));
+            }
+        }
+        if let Some(namespace) = namespace {
+            if let Some(id) = table.definition_at(namespace.span.start) {
+                namespaces.push((id, exports, path));
+            }
+        }
+    }
+
+    if !namespaces.is_empty() {
+        let mut members = Vec::new();
+        collect_members(&module.statements, &mut members);
+        for (target, member) in members {
+            let Some(id) = table.definition_at(target.span.start) else {
+                continue;
+            };
+            let Some((_, exports, path)) = namespaces.iter().find(|(ns, _, _)| *ns == id) else {
+                continue;
+            };
+            if !member.name.is_empty() && !exports.contains(&member.name) {
+                diagnostics.push(unknown_export(path, &member.name, exports, member.span));
+            }
+        }
+    }
+    diagnostics
+}
+
+fn unknown_export(path: &str, name: &str, exports: &[String], span: Span) -> Diagnostic {
+    let similar = similar_names(name, exports.iter().map(String::as_str));
+    Diagnostic::error(format!("`{}` has no export named `{}`", path, name), span)
+        .with_suggestions(similar.into_iter().map(str::to_string).collect())
+}
+
+// Every `name.member` access, which may be a member of a namespace import
+fn collect_members<'a>(body: &'a [Stmt], members: &mut Vec<(&'a Ident, &'a Ident)>) {
+    for stmt in body {
+        match stmt {
+            Stmt::Var { value, .. }
+            | Stmt::Const { value, .. }
+            | Stmt::Throw { value, .. }
+            | Stmt::Expr(value) => collect_members_in_expr(value, members),
+            Stmt::Function { body, .. } => collect_members(&body.statements, members),
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                collect_members(&body.statements, members);
+                if let Some(CatchClause { body, .. }) = catch {
+                    collect_members(&body.statements, members);
+                }
+                if let Some(finally) = finally {
+                    collect_members(&finally.statements, members);
+                }
+            }
+            Stmt::Import { .. } | Stmt::ExternFunction { .. } => {}
+        }
+    }
+}
+
+fn collect_members_in_expr<'a>(expr: &'a Expr, members: &mut Vec<(&'a Ident, &'a Ident)>) {
+    match expr {
+        Expr::Integer(_) | Expr::Float(_) | Expr::String(_) | Expr::Ident(_) | Expr::Error(_) => {}
+        Expr::DotAccess { target, name } => match target.as_ref() {
+            Expr::Ident(target) => members.push((target, name)),
+            target => collect_members_in_expr(target, members),
+        },
+        Expr::BinaryOp { left, right, .. } => {
+            collect_members_in_expr(left, members);
+            collect_members_in_expr(right, members);
+        }
+        Expr::Call { target, arguments } => {
+            collect_members_in_expr(target, members);
+            for arg in arguments {
+                collect_members_in_expr(&arg.expr, members);
+            }
+        }
+        Expr::BracketAccess { target, expr } => {
+            collect_members_in_expr(target, members);
+            collect_members_in_expr(expr, members);
+        }
+        Expr::Await(expr) | Expr::Cast { expr, .. } => collect_members_in_expr(expr, members),
+        Expr::If {
+            branches,
+            else_body,
+        } => {
+            for IfBranch { condition, body } in branches {
+                collect_members_in_expr(condition, members);
+                collect_members(&body.statements, members);
+            }
+            if let Some(body) = else_body {
+                collect_members(&body.statements, members);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::{check, exports, ImportedExports};
+
+    fn errors(source: &str) -> Vec<String> {
+        let math =
+            parse("const PI = 3.14\nfn sin(x: float): float = x\nfn cos(x: float): float = x")
+                .unwrap();
+        let utils =
+            parse("pub import { sin as sine } from \"./math.sky\"\nlet (first, second) = pair()")
+                .unwrap();
+        let imported = ImportedExports::from([
+            ("./math.sky".to_string(), exports(&math)),
+            ("./utils.sky".to_string(), exports(&utils)),
+        ]);
+        check(&parse(source).expect("source should parse"), &imported)
+            .into_iter()
+            .map(|d| d.render(source))
+            .collect()
+    }
+
+    #[test]
+    fn imported_symbols() {
+        assert_eq!(
+            errors("import { PI, cos as cosine, sinn } from \"./math.sky\"\nimport { x } from \"./missing.sky\""),
+            vec!["error at 1:29: `./math.sky` has no export named `sinn`, did you mean `sin`?"]
+        );
+        assert!(errors("import { sine, first, second } from \"./utils.sky\"").is_empty());
+    }
+
+    #[test]
+    fn namespace_members() {
+        assert_eq!(
+            errors("import * as math from \"./math.sky\"\nfn main() {\n    math.sin(math.PI)\n    math.tan(1)\n    let math = other\n    math.tan\n}"),
+            vec!["error at 4:10: `./math.sky` has no export named `tan`"]
+        );
+    }
+}
@@ -1 +1,26 @@
+use crate::{error::Diagnostic, parser::ast::Module};
 
+use self::{consts::ImportedConsts, imports::ImportedExports};
+
+pub mod consts;
+pub mod imports;
+mod names;
+pub mod numeric;
+mod suggest;
+pub mod symbols;
+mod unused;
+
+/// Runs every analysis pass over the module
+pub fn analyze(
+    module: &Module,
+    imported: &ImportedConsts,
+    exports: &ImportedExports,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = unused::check(module);
+    diagnostics.extend(names::check(module));
+    diagnostics.extend(imports::check(module, exports));
+    diagnostics.extend(consts::eval_consts(module, imported).1);
+    diagnostics.extend(numeric::check(module, imported));
+    diagnostics.sort_by_key(|d| d.span.start);
+    diagnostics
+}
@@ -0,0 +1,72 @@
+//! Names used without being declared.
+
+use crate::{
+    analyzer::{suggest::similar_names, symbols::SymbolTable},
+    error::Diagnostic,
+    parser::ast::Module,
+};
+
+/// Warnings for names which aren't declared in any scope they are used
+/// in but are close to a name in scope, which is suggested instead.
+/// Other undeclared names may be globals of the host, like `console`,
+/// and aren't reported.
+pub fn check(module: &Module) -> Vec<Diagnostic> {
+    let table = SymbolTable::build(module);
+    table
+        .unresolved()
+        .filter_map(|name| {
+            let in_scope = table.symbols_in_scope(name.span.start);
+            let candidates = in_scope
+                .iter()
+                .map(|&id| table.symbol(id).name.name.as_str());
+            let similar = similar_names(&name.name, candidates);
+            if similar.is_empty() {
+                return None;
+            }
+            Some(
+                Diagnostic::warning(
+                    format!("cannot find `{}` in this scope", name.name),
+                    name.span,
+                )
+                .with_suggestions(similar.into_iter().map(str::to_string).collect()),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::check;
+
+    fn warnings(source: &str) -> Vec<String> {
+        check(&parse(source).expect("source should parse"))
+            .into_iter()
+            .map(|d| d.render(source))
+            .collect()
+    }
+
+    #[test]
+    fn suggests_similar_names() {
+        assert_eq!(
+            warnings("extern fn print(s: string)\nfn main() {\n    let count = 1\n    let counter = 2\n    prnt(cont)\n    cout\n}"),
+            vec![
+                "warning at 5:5: cannot find `prnt` in this scope, did you mean `print`?",
+                "warning at 5:10: cannot find `cont` in this scope, did you mean `count`?",
+                "warning at 6:5: cannot find `cout` in this scope, did you mean `count`?",
+            ]
+        );
+        assert_eq!(
+            warnings("let alpha = 1\nlet alpah = 2\nlet alphas = 3\nlet alp = 4\nalpa"),
+            vec!["warning at 5:1: cannot find `alpa` in this scope, did you mean `alp`, `alpah` or `alpha`?"]
+        );
+        assert!(warnings("fn f(): int = g()\nfn g(): int = 1").is_empty());
+    }
+
+    #[test]
+    fn host_globals_are_not_reported() {
+        assert!(warnings("let message = \"hi\"\nconsole.log(message)\nalert(message)").is_empty());
+        assert!(warnings("let x = 1\ny").is_empty());
+    }
+}
@@ -0,0 +1,553 @@
+//! Numeric types and the rules for mixing them.
+//!
+//! A value is implicitly widened only when every value of its type is
+//! exactly representable in the wider type, everything else requires an
+//! explicit `as` cast. `int` and `float` are aliases of `i32` and `f64`.
+
+use std::{collections::HashMap, fmt, mem};
+
+use super::consts::{ConstValue, ImportedConsts};
+use crate::{
+    error::Diagnostic,
+    parser::ast::{
+        pattern::Pattern, BinaryOpKind, Block, CatchClause, Expr, IfBranch, Module, Span, Stmt,
+        TypeUsage,
+    },
+};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NumType {
+    I32,
+    I64,
+    U32,
+    U64,
+    F32,
+    F64,
+}
+
+impl NumType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "i32" | "int" => Some(NumType::I32),
+            "i64" => Some(NumType::I64),
+            "u32" => Some(NumType::U32),
+            "u64" => Some(NumType::U64),
+            "f32" => Some(NumType::F32),
+            "f64" | "float" => Some(NumType::F64),
+            _ => None,
+        }
+    }
+
+    pub fn from_usage(t: &TypeUsage) -> Option<Self> {
+        if t.params.is_empty() {
+            Self::from_name(&t.name)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, NumType::F32 | NumType::F64)
+    }
+
+    /// Whether every value of `self` converts to `to` without loss
+    pub fn widens_to(&self, to: NumType) -> bool {
+        use NumType::*;
+
+        *self == to
+            || matches!(
+                (self, to),
+                (I32, I64) | (U32, U64) | (U32, I64) | (I32, F64) | (U32, F64) | (F32, F64)
+            )
+    }
+
+    /// Type both operands of a binary operation are converted to,
+    /// `None` when one of them would lose precision
+    pub fn common(a: NumType, b: NumType) -> Option<NumType> {
+        if a.widens_to(b) {
+            Some(b)
+        } else if b.widens_to(a) {
+            Some(a)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for NumType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            NumType::I32 => "i32",
+            NumType::I64 => "i64",
+            NumType::U32 => "u32",
+            NumType::U64 => "u64",
+            NumType::F32 => "f32",
+            NumType::F64 => "f64",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Type of an expression as far as the numeric checks are concerned
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Ty {
+    Num(NumType),
+    /// Integer literal, fits any numeric type
+    IntLiteral,
+    /// Float literal, fits any float type
+    FloatLiteral,
+    /// Function returning the type
+    Fn(Option<NumType>),
+    Unknown,
+}
+
+/// Reports binary operations mixing numeric types without an explicit cast
+/// and casts to types which aren't numeric
+pub fn check(module: &Module, imported: &ImportedConsts) -> Vec<Diagnostic> {
+    let mut checker = Checker::new(imported);
+    checker.check_body(&module.statements);
+    checker.diagnostics
+}
+
+/// Wraps every division of integers in a cast to their type, so the
+/// quotient is truncated towards zero like `as` truncates floats
+pub fn truncate_integer_division(module: &mut Module, imported: &ImportedConsts) {
+    let mut checker = Checker::new(imported);
+    checker.check_body(&module.statements);
+    truncate_in_body(&mut module.statements, &mut checker.divisions.into_iter());
+}
+
+struct Checker<'a> {
+    scopes: Vec<HashMap<String, Ty>>,
+    imported: &'a ImportedConsts,
+    /// Span of the innermost statement or block being checked, errors
+    /// in expressions made of literals only are reported there
+    context: Span,
+    /// Integer type of every division in the order they are checked,
+    /// `None` for divisions of floats or unknown types
+    divisions: Vec<Option<NumType>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+    fn new(imported: &'a ImportedConsts) -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+            imported,
+            context: Span::default(),
+            divisions: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn declare(&mut self, name: &str, ty: Ty) {
+        self.scopes
+            .last_mut()
+            .expect("bindings are declared inside a scope")
+            .insert(name.to_string(), ty);
+    }
+
+    fn check_block(&mut self, block: &Block) {
+        let outer = mem::replace(&mut self.context, block.span);
+        self.scopes.push(HashMap::new());
+        self.check_body(&block.statements);
+        self.scopes.pop();
+        self.context = outer;
+    }
+
+    fn check_body(&mut self, body: &[Stmt]) {
+        for stmt in body {
+            if let Stmt::Function { name, ret_type, .. }
+            | Stmt::ExternFunction { name, ret_type, .. } = stmt
+            {
+                self.declare(&name.name, Ty::Fn(NumType::from_usage(ret_type)));
+            }
+        }
+        for stmt in body {
+            let outer = self.context;
+            if let Some(span) = stmt_span(stmt) {
+                self.context = span;
+            }
+            self.check_stmt(stmt);
+            self.context = outer;
+        }
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Var {
+                pattern: Pattern::Binding(name),
+                value,
+                ..
+            }
+            | Stmt::Const { name, value } => {
+                let ty = self.check_expr(value);
+                self.declare(&name.name, ty);
+            }
+            // Parts of values aren't typed
+            Stmt::Var { pattern, value, .. } => {
+                self.check_expr(value);
+                for name in pattern.bindings() {
+                    self.declare(&name.name, Ty::Unknown);
+                }
+            }
+            Stmt::Function { params, body, .. } => {
+                self.scopes.push(HashMap::new());
+                for param in params {
+                    let ty = NumType::from_usage(&param.r#type).map_or(Ty::Unknown, Ty::Num);
+                    self.declare(&param.name.name, ty);
+                }
+                self.check_block(body);
+                self.scopes.pop();
+            }
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.check_block(body);
+                if let Some(CatchClause { binding, body }) = catch {
+                    self.scopes.push(HashMap::new());
+                    if let Some(binding) = binding {
+                        self.declare(&binding.name, Ty::Unknown);
+                    }
+                    self.check_block(body);
+                    self.scopes.pop();
+                }
+                if let Some(finally) = finally {
+                    self.check_block(finally);
+                }
+            }
+            Stmt::Throw { value, .. } | Stmt::Expr(value) => {
+                self.check_expr(value);
+            }
+            Stmt::Import {
+                symbols,
+                namespace,
+                path,
+                ..
+            } => {
+                // Imported constants are typed like the literal they evaluate to
+                let consts = self.imported.get(path.as_str());
+                for sym in symbols {
+                    let local = sym.imported_as.as_ref().unwrap_or(&sym.name);
+                    let ty = match consts.and_then(|c| c.get(&sym.name)) {
+                        Some(ConstValue::Integer(_)) => Ty::IntLiteral,
+                        Some(ConstValue::Float(_)) => Ty::FloatLiteral,
+                        _ => Ty::Unknown,
+                    };
+                    self.declare(local, ty);
+                }
+                if let Some(namespace) = namespace {
+                    self.declare(&namespace.name, Ty::Unknown);
+                }
+            }
+            Stmt::ExternFunction { .. } => {}
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Ty {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .copied()
+            .unwrap_or(Ty::Unknown)
+    }
+
+    fn error(&mut self, message: String, expr: &Expr) {
+        let span = expr_span(expr).unwrap_or(self.context);
+        self.diagnostics.push(Diagnostic::error(message, span));
+    }
+
+    fn check_expr(&mut self, expr: &Expr) -> Ty {
+        match expr {
+            Expr::Integer(_) => Ty::IntLiteral,
+            Expr::Float(_) => Ty::FloatLiteral,
+            Expr::String(_) | Expr::Error(_) => Ty::Unknown,
+            Expr::Ident(name) => self.lookup(&name.name),
+            Expr::BinaryOp { kind, left, right } => {
+                let l = self.check_expr(left);
+                let r = self.check_expr(right);
+                let ty = match binary_type(l, r) {
+                    Ok(ty) => ty,
+                    Err((a, b)) => {
+                        self.error(
+                            format!(
+                                "`{}` can't implicitly mix `{}` and `{}` without losing precision, use an explicit `as` cast",
+                                kind.to_op(),
+                                a,
+                                b
+                            ),
+                            expr,
+                        );
+                        Ty::Unknown
+                    }
+                };
+                if *kind == BinaryOpKind::Div {
+                    self.divisions.push(match ty {
+                        Ty::Num(t) if !t.is_float() => Some(t),
+                        Ty::IntLiteral => Some(NumType::I32),
+                        _ => None,
+                    });
+                }
+                ty
+            }
+            Expr::Call { target, arguments } => {
+                let target = self.check_expr(target);
+                for arg in arguments {
+                    self.check_expr(&arg.expr);
+                }
+                match target {
+                    Ty::Fn(Some(ret)) => Ty::Num(ret),
+                    _ => Ty::Unknown,
+                }
+            }
+            Expr::DotAccess { target, .. } => {
+                self.check_expr(target);
+                Ty::Unknown
+            }
+            Expr::BracketAccess { target, expr } => {
+                self.check_expr(target);
+                self.check_expr(expr);
+                Ty::Unknown
+            }
+            Expr::Await(expr) => {
+                self.check_expr(expr);
+                Ty::Unknown
+            }
+            Expr::Cast { expr, r#type } => {
+                self.check_expr(expr);
+                match NumType::from_usage(r#type) {
+                    Some(to) => Ty::Num(to),
+                    None => {
+                        self.error(
+                            format!(
+                                "can't cast to `{}`, `as` only converts between numeric types",
+                                r#type.dump()
+                            ),
+                            expr,
+                        );
+                        Ty::Unknown
+                    }
+                }
+            }
+            Expr::If {
+                branches,
+                else_body,
+            } => {
+                for IfBranch { condition, body } in branches {
+                    self.check_expr(condition);
+                    self.check_block(body);
+                }
+                if let Some(body) = else_body {
+                    self.check_block(body);
+                }
+                Ty::Unknown
+            }
+        }
+    }
+}
+
+// Span of the statement's name or keyword, if it has one
+fn stmt_span(stmt: &Stmt) -> Option<Span> {
+    match stmt {
+        Stmt::Var { pattern, .. } => pattern.bindings().first().map(|name| name.span),
+        Stmt::Const { name, .. } => Some(name.span),
+        Stmt::Throw { span, .. } => Some(*span),
+        _ => None,
+    }
+}
+
+// Visits the divisions in the same order as `Checker`, wrapping the ones
+// of integers in a cast
+fn truncate_in_body(body: &mut [Stmt], divisions: &mut impl Iterator<Item = Option<NumType>>) {
+    for stmt in body {
+        match stmt {
+            Stmt::Var { value, .. }
+            | Stmt::Const { value, .. }
+            | Stmt::Throw { value, .. }
+            | Stmt::Expr(value) => truncate_in_expr(value, divisions),
+            Stmt::Function { body, .. } => truncate_in_body(&mut body.statements, divisions),
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                truncate_in_body(&mut body.statements, divisions);
+                if let Some(CatchClause { body, .. }) = catch {
+                    truncate_in_body(&mut body.statements, divisions);
+                }
+                if let Some(finally) = finally {
+                    truncate_in_body(&mut finally.statements, divisions);
+                }
+            }
+            Stmt::Import { .. } | Stmt::ExternFunction { .. } => {}
+        }
+    }
+}
+
+fn truncate_in_expr(expr: &mut Expr, divisions: &mut impl Iterator<Item = Option<NumType>>) {
+    match expr {
+        Expr::Integer(_) | Expr::Float(_) | Expr::String(_) | Expr::Ident(_) | Expr::Error(_) => {}
+        Expr::BinaryOp { kind, left, right } => {
+            truncate_in_expr(left, divisions);
+            truncate_in_expr(right, divisions);
+            if *kind != BinaryOpKind::Div {
+                return;
+            }
+            let Some(to) = divisions.next().expect("every division is checked") else {
+                return;
+            };
+            let division = mem::replace(expr, Expr::Error(Span::default()));
+            *expr = Expr::Cast {
+                expr: Box::new(division),
+                r#type: TypeUsage::from_name(&to.to_string()),
+            };
+        }
+        Expr::Call { target, arguments } => {
+            truncate_in_expr(target, divisions);
+            for arg in arguments {
+                truncate_in_expr(&mut arg.expr, divisions);
+            }
+        }
+        Expr::BracketAccess { target, expr } => {
+            truncate_in_expr(target, divisions);
+            truncate_in_expr(expr, divisions);
+        }
+        Expr::DotAccess { target: expr, .. } | Expr::Await(expr) | Expr::Cast { expr, .. } => {
+            truncate_in_expr(expr, divisions)
+        }
+        Expr::If {
+            branches,
+            else_body,
+        } => {
+            for IfBranch { condition, body } in branches {
+                truncate_in_expr(condition, divisions);
+                truncate_in_body(&mut body.statements, divisions);
+            }
+            if let Some(body) = else_body {
+                truncate_in_body(&mut body.statements, divisions);
+            }
+        }
+    }
+}
+
+// Mismatched operand types are returned as the error
+fn binary_type(l: Ty, r: Ty) -> Result<Ty, (NumType, String)> {
+    match (l, r) {
+        (Ty::Num(a), Ty::Num(b)) => NumType::common(a, b).map(Ty::Num).ok_or((a, b.to_string())),
+        (Ty::Num(t), Ty::FloatLiteral) | (Ty::FloatLiteral, Ty::Num(t)) if !t.is_float() => {
+            Err((t, "{float}".to_string()))
+        }
+        (Ty::Num(t), Ty::IntLiteral | Ty::FloatLiteral)
+        | (Ty::IntLiteral | Ty::FloatLiteral, Ty::Num(t)) => Ok(Ty::Num(t)),
+        (Ty::IntLiteral, Ty::IntLiteral) => Ok(Ty::IntLiteral),
+        (Ty::IntLiteral | Ty::FloatLiteral, Ty::IntLiteral | Ty::FloatLiteral) => {
+            Ok(Ty::FloatLiteral)
+        }
+        _ => Ok(Ty::Unknown),
+    }
+}
+
+// Literals carry no position, so the span covers the names used in the expression
+fn expr_span(expr: &Expr) -> Option<Span> {
+    let join = |a: Option<Span>, b: Option<Span>| match (a, b) {
+        (Some(a), Some(b)) => Some(Span::new(a.start.min(b.start), a.end.max(b.end))),
+        (a, b) => a.or(b),
+    };
+    match expr {
+        Expr::Integer(_) | Expr::Float(_) | Expr::String(_) => None,
+        Expr::Ident(name) => Some(name.span),
+        Expr::Error(span) => Some(*span),
+        Expr::BinaryOp { left, right, .. } => join(expr_span(left), expr_span(right)),
+        Expr::Call { target, arguments } => {
+            arguments.iter().fold(expr_span(target), |span, arg| {
+                join(span, expr_span(&arg.expr))
+            })
+        }
+        Expr::DotAccess { target, .. } | Expr::Await(target) | Expr::Cast { expr: target, .. } => {
+            expr_span(target)
+        }
+        Expr::BracketAccess { target, expr } => join(expr_span(target), expr_span(expr)),
+        Expr::If { branches, .. } => branches.iter().fold(None, |span, branch| {
+            join(span, expr_span(&branch.condition))
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::parser::parse;
+
+    use super::{check, NumType};
+
+    fn errors(source: &str) -> Vec<String> {
+        check(&parse(source).unwrap(), &HashMap::new())
+            .into_iter()
+            .map(|d| d.render(source))
+            .collect()
+    }
+
+    #[test]
+    fn widening() {
+        assert!(NumType::I32.widens_to(NumType::I64));
+        assert!(NumType::U32.widens_to(NumType::I64));
+        assert!(NumType::I32.widens_to(NumType::F64));
+        assert!(!NumType::I32.widens_to(NumType::F32));
+        assert!(!NumType::I64.widens_to(NumType::F64));
+        assert!(!NumType::I32.widens_to(NumType::U32));
+        assert_eq!(
+            NumType::common(NumType::F32, NumType::F64),
+            Some(NumType::F64)
+        );
+        assert_eq!(NumType::common(NumType::I64, NumType::F32), None);
+    }
+
+    #[test]
+    fn lossless_mixes_are_allowed() {
+        assert!(errors(
+            "fn f(a: i32, b: i64, c: f32, d: float, e: u32): f64 {\n    let x = a + b * 2\n    let y = c + d + a + 0.5\n    let z = e + b\n    x as f64 + y\n}"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn lossy_mixes_need_a_cast() {
+        assert_eq!(
+            errors(
+                "fn big(): u64 = 1\nfn f(a: i64, b: f32, c: int) {\n    let x = a + b\n    let y = c * 1.5\n    let z = big() - c\n    let w = a as f32 + b\n}"
+            ),
+            vec![
+                "error at 3:13: `+` can't implicitly mix `i64` and `f32` without losing precision, use an explicit `as` cast",
+                "error at 4:13: `*` can't implicitly mix `i32` and `{float}` without losing precision, use an explicit `as` cast",
+                "error at 5:13: `-` can't implicitly mix `u64` and `i32` without losing precision, use an explicit `as` cast",
+            ]
+        );
+    }
+
+    #[test]
+    fn casts_to_numeric_types_only() {
+        assert_eq!(
+            errors("fn f(a: i32) {\n    let s = a as string\n    let l = a as List<i32>\n    let x = a as u32\n}"),
+            vec![
+                "error at 2:13: can't cast to `string`, `as` only converts between numeric types",
+                "error at 3:13: can't cast to `List<i32>`, `as` only converts between numeric types",
+            ]
+        );
+    }
+
+    #[test]
+    fn literal_errors_are_reported_at_their_statement() {
+        assert_eq!(
+            errors("const A = 1.5 as string\nfn f() {\n    let x = 2.5 * 3 as u32\n}\nfn g(): i32 = 1 as string"),
+            vec![
+                "error at 1:7: can't cast to `string`, `as` only converts between numeric types",
+                "error at 3:9: `*` can't implicitly mix `u32` and `{float}` without losing precision, use an explicit `as` cast",
+                "error at 5:15: can't cast to `string`, `as` only converts between numeric types",
+            ]
+        );
+    }
+}
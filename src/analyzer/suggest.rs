@@ -0,0 +1,65 @@
+//! Suggestions for misspelled names.
+
+/// Candidates close to `name` by edit distance, closest first and at
+/// most three of them. The allowed distance grows with the length of
+/// the name: names shorter than three characters match nothing and
+/// names shorter than six only match names with a single typo.
+pub fn similar_names<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Vec<&'a str> {
+    let max_distance = name.chars().count() / 3;
+    let mut found = candidates
+        .into_iter()
+        .filter(|&c| c != name)
+        .map(|c| (edit_distance(name, c), c))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .collect::<Vec<_>>();
+    // Stable, so candidates at the same distance keep their order
+    found.sort_by_key(|&(distance, _)| distance);
+    let mut names: Vec<&str> = Vec::new();
+    for (_, c) in found {
+        if !names.contains(&c) {
+            names.push(c);
+        }
+    }
+    names.truncate(3);
+    names
+}
+
+// Levenshtein distance: insertions, deletions and substitutions
+// of single characters needed to turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{edit_distance, similar_names};
+
+    #[test]
+    fn distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("same", "same"), 0);
+    }
+
+    #[test]
+    fn closest_first() {
+        let names = ["cos", "sin", "sinh", "tan", "asin", "sqrt"];
+        assert_eq!(similar_names("sinn", names), vec!["sin", "sinh"]);
+        assert_eq!(similar_names("coz", names), vec!["cos"]);
+        assert!(similar_names("log", names).is_empty());
+        assert!(similar_names("co", names).is_empty());
+    }
+}
@@ -0,0 +1,531 @@
+//! Bindings of a module and every place they are used.
+//!
+//! Names are resolved the same way the generated code resolves them:
+//! functions can be used anywhere in their block, everything else only
+//! after it is declared, and inner declarations shadow outer ones.
+
+use crate::parser::ast::{
+    Block, CatchClause, Expr, FunctionParam, Ident, IfBranch, Module, Span, Stmt, TypeUsage,
+};
+
+/// Index of a symbol in its [`SymbolTable`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct SymbolId(usize);
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SymbolKind {
+    Variable,
+    Constant,
+    Parameter,
+    Function,
+    /// Function implemented by the host
+    ExternFunction,
+    Import,
+    /// Error bound by a `catch` clause
+    CatchBinding,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Symbol {
+    pub name: Ident,
+    pub kind: SymbolKind,
+    /// Signature of functions and the type of parameters
+    pub detail: Option<String>,
+    /// Function the symbol is declared in, `None` at module level
+    pub container: Option<SymbolId>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Reference {
+    pub symbol: SymbolId,
+    pub span: Span,
+    /// Function the reference is in, `None` at module level
+    pub container: Option<SymbolId>,
+}
+
+/// Symbol with the symbols declared inside of it, see [`SymbolTable::document_symbols`]
+#[derive(Debug, PartialEq, Clone)]
+pub struct DocumentSymbol {
+    pub symbol: SymbolId,
+    pub children: Vec<DocumentSymbol>,
+}
+
+#[derive(Debug)]
+struct Scope {
+    span: Span,
+    parent: Option<usize>,
+    /// Symbols declared in the scope, in the order of declaration
+    symbols: Vec<SymbolId>,
+}
+
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+    references: Vec<Reference>,
+    scopes: Vec<Scope>,
+    /// Names which didn't resolve, with the scope they are used in
+    unresolved: Vec<(Ident, usize)>,
+}
+
+impl SymbolTable {
+    pub fn build(module: &Module) -> Self {
+        let mut resolver = Resolver::default();
+        resolver.walk_block(Span::new(0, usize::MAX), &module.statements);
+        // Function bodies are walked after the rest of their block,
+        // uses are kept in source order
+        resolver.table.references.sort_by_key(|r| r.span.start);
+        resolver
+            .table
+            .unresolved
+            .sort_by_key(|(name, _)| name.span.start);
+        resolver.table
+    }
+
+    pub fn symbol(&self, id: SymbolId) -> &Symbol {
+        &self.symbols[id.0]
+    }
+
+    /// Every symbol in the order of declaration
+    pub fn symbols(&self) -> impl Iterator<Item = (SymbolId, &Symbol)> {
+        self.symbols
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (SymbolId(i), s))
+    }
+
+    /// Whether the symbol is declared at module level, outside of any block
+    pub fn is_top_level(&self, id: SymbolId) -> bool {
+        self.scopes[0].symbols.contains(&id)
+    }
+
+    /// Symbol whose name or one of whose references is at the offset
+    pub fn definition_at(&self, offset: usize) -> Option<SymbolId> {
+        self.references
+            .iter()
+            .find(|r| r.span.contains(offset))
+            .map(|r| r.symbol)
+            .or_else(|| {
+                self.symbols()
+                    .find(|(_, s)| s.name.span.contains(offset))
+                    .map(|(id, _)| id)
+            })
+    }
+
+    /// Uses of the symbol in source order, without its declaration
+    pub fn references_of(&self, symbol: SymbolId) -> impl Iterator<Item = &Reference> {
+        self.references.iter().filter(move |r| r.symbol == symbol)
+    }
+
+    /// Symbols which can be used at the offset, closest first.
+    /// Shadowed symbols are left out.
+    pub fn symbols_in_scope(&self, offset: usize) -> Vec<SymbolId> {
+        // Scopes are created before the scopes nested in them,
+        // so the last one containing the offset is the innermost
+        let mut scope = self.scopes.iter().rposition(|s| s.span.contains(offset));
+        let mut found: Vec<SymbolId> = Vec::new();
+        while let Some(index) = scope {
+            for &id in self.scopes[index].symbols.iter().rev() {
+                let symbol = self.symbol(id);
+                let visible = symbol.kind == SymbolKind::Function || symbol.name.span.end <= offset;
+                if visible
+                    && !found
+                        .iter()
+                        .any(|&f| self.symbol(f).name.name == symbol.name.name)
+                {
+                    found.push(id);
+                }
+            }
+            scope = self.scopes[index].parent;
+        }
+        found
+    }
+
+    /// Names which aren't declared in any scope they are used in.
+    ///
+    /// A name declared in one of those scopes only after it is used
+    /// doesn't count, e.g. a variable of the module used by a function
+    /// defined before it, which may run after the variable is set.
+    pub fn unresolved(&self) -> impl Iterator<Item = &Ident> {
+        self.unresolved
+            .iter()
+            .filter(|(name, scope)| {
+                let mut scope = Some(*scope);
+                while let Some(index) = scope {
+                    let declared = self.scopes[index]
+                        .symbols
+                        .iter()
+                        .any(|&id| self.symbol(id).name.name == name.name);
+                    if declared {
+                        return false;
+                    }
+                    scope = self.scopes[index].parent;
+                }
+                true
+            })
+            .map(|(name, _)| name)
+    }
+
+    /// Outline of the module: its declarations with the declarations
+    /// of functions nested under them. Parameters and catch bindings
+    /// are left out.
+    pub fn document_symbols(&self) -> Vec<DocumentSymbol> {
+        self.children_of(None)
+    }
+
+    fn children_of(&self, container: Option<SymbolId>) -> Vec<DocumentSymbol> {
+        let mut children = self
+            .symbols()
+            .filter(|(_, s)| s.container == container)
+            .filter(|(_, s)| !matches!(s.kind, SymbolKind::Parameter | SymbolKind::CatchBinding))
+            .map(|(id, _)| DocumentSymbol {
+                symbol: id,
+                children: self.children_of(Some(id)),
+            })
+            .collect::<Vec<_>>();
+        children.sort_by_key(|c| self.symbol(c.symbol).name.span.start);
+        children
+    }
+}
+
+#[derive(Default)]
+struct Resolver {
+    table: SymbolTable,
+    scope: Option<usize>,
+    container: Option<SymbolId>,
+}
+
+impl Resolver {
+    fn push_scope(&mut self, span: Span) {
+        self.table.scopes.push(Scope {
+            span,
+            parent: self.scope,
+            symbols: Vec::new(),
+        });
+        self.scope = Some(self.table.scopes.len() - 1);
+    }
+
+    fn pop_scope(&mut self) {
+        let scope = self.scope.expect("scopes are balanced");
+        self.scope = self.table.scopes[scope].parent;
+    }
+
+    fn declare(&mut self, name: &Ident, kind: SymbolKind, detail: Option<String>) -> SymbolId {
+        let id = SymbolId(self.table.symbols.len());
+        self.table.symbols.push(Symbol {
+            name: name.clone(),
+            kind,
+            detail,
+            container: self.container,
+        });
+        let scope = self.scope.expect("symbols are declared inside a scope");
+        self.table.scopes[scope].symbols.push(id);
+        id
+    }
+
+    fn resolve(&mut self, name: &Ident) {
+        let mut scope = self.scope;
+        while let Some(index) = scope {
+            let found = self.table.scopes[index]
+                .symbols
+                .iter()
+                .rev()
+                .find(|&&id| self.table.symbol(id).name.name == name.name);
+            if let Some(&symbol) = found {
+                self.table.references.push(Reference {
+                    symbol,
+                    span: name.span,
+                    container: self.container,
+                });
+                return;
+            }
+            scope = self.table.scopes[index].parent;
+        }
+        if let Some(scope) = self.scope {
+            self.table.unresolved.push((name.clone(), scope));
+        }
+    }
+
+    fn walk_block(&mut self, span: Span, statements: &[Stmt]) {
+        self.push_scope(span);
+
+        // Functions can be used before they are defined in a block
+        let mut functions = Vec::new();
+        for stmt in statements {
+            if let Stmt::Function {
+                name,
+                is_async,
+                params,
+                ret_type,
+                ..
+            } = stmt
+            {
+                let detail = signature(&name.name, *is_async, params, ret_type);
+                functions.push(self.declare(name, SymbolKind::Function, Some(detail)));
+            }
+        }
+
+        for stmt in statements {
+            self.walk_stmt(stmt);
+        }
+        // Function bodies can use everything declared in the block,
+        // they may run after the rest of it
+        let bodies = statements.iter().filter_map(|stmt| match stmt {
+            Stmt::Function {
+                name, params, body, ..
+            } => Some((name, params, body)),
+            _ => None,
+        });
+        for (id, (name, params, body)) in functions.into_iter().zip(bodies) {
+            self.walk_function(id, name, params, body);
+        }
+
+        self.pop_scope();
+    }
+
+    fn walk_body(&mut self, block: &Block) {
+        self.walk_block(block.span, &block.statements);
+    }
+
+    fn walk_function(
+        &mut self,
+        id: SymbolId,
+        name: &Ident,
+        params: &[FunctionParam],
+        body: &Block,
+    ) {
+        let container = self.container.replace(id);
+        self.push_scope(Span::new(name.span.end, body.span.end));
+        for param in params {
+            self.declare(
+                &param.name,
+                SymbolKind::Parameter,
+                Some(param.r#type.dump()),
+            );
+        }
+        self.walk_body(body);
+        self.pop_scope();
+        self.container = container;
+    }
+
+    fn walk_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Import {
+                symbols, namespace, ..
+            } => {
+                for sym in symbols {
+                    let local = sym.imported_as.as_ref().unwrap_or(&sym.name);
+                    self.declare(&Ident::new(local, sym.span), SymbolKind::Import, None);
+                }
+                if let Some(namespace) = namespace {
+                    self.declare(namespace, SymbolKind::Import, None);
+                }
+            }
+            Stmt::Var { pattern, value, .. } => {
+                self.walk_expr(value);
+                for name in pattern.bindings() {
+                    self.declare(name, SymbolKind::Variable, None);
+                }
+            }
+            Stmt::Const { name, value } => {
+                self.walk_expr(value);
+                self.declare(name, SymbolKind::Constant, None);
+            }
+            Stmt::ExternFunction {
+                name,
+                is_async,
+                params,
+                ret_type,
+            } => {
+                let detail = signature(&name.name, *is_async, params, ret_type);
+                self.declare(name, SymbolKind::ExternFunction, Some(detail));
+            }
+            Stmt::Function { .. } => {}
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.walk_body(body);
+                if let Some(CatchClause { binding, body }) = catch {
+                    let start = binding.as_ref().map_or(body.span.start, |b| b.span.start);
+                    self.push_scope(Span::new(start, body.span.end));
+                    if let Some(binding) = binding {
+                        self.declare(binding, SymbolKind::CatchBinding, None);
+                    }
+                    self.walk_body(body);
+                    self.pop_scope();
+                }
+                if let Some(finally) = finally {
+                    self.walk_body(finally);
+                }
+            }
+            Stmt::Throw { value, .. } | Stmt::Expr(value) => self.walk_expr(value),
+        }
+    }
+
+    fn walk_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Integer(_) | Expr::Float(_) | Expr::String(_) | Expr::Error(_) => {}
+            Expr::Ident(name) => self.resolve(name),
+            Expr::BinaryOp { left, right, .. } => {
+                self.walk_expr(left);
+                self.walk_expr(right);
+            }
+            Expr::Call { target, arguments } => {
+                self.walk_expr(target);
+                for arg in arguments {
+                    self.walk_expr(&arg.expr);
+                }
+            }
+            Expr::DotAccess { target, .. } => self.walk_expr(target),
+            Expr::BracketAccess { target, expr } => {
+                self.walk_expr(target);
+                self.walk_expr(expr);
+            }
+            Expr::Await(expr) | Expr::Cast { expr, .. } => self.walk_expr(expr),
+            Expr::If {
+                branches,
+                else_body,
+            } => {
+                for IfBranch { condition, body } in branches {
+                    self.walk_expr(condition);
+                    self.walk_body(body);
+                }
+                if let Some(body) = else_body {
+                    self.walk_body(body);
+                }
+            }
+        }
+    }
+}
+
+// Signature as written in the source, `async fn f(a: int): int`
+fn signature(name: &str, is_async: bool, params: &[FunctionParam], ret_type: &TypeUsage) -> String {
+    let params = params
+        .iter()
+        .map(|p| format!("{}: {}", p.name.name, p.r#type.dump()))
+        .collect::<Vec<_>>();
+    format!(
+        "{}fn {}({}): {}",
+        if is_async { "async " } else { "" },
+        name,
+        params.join(", "),
+        ret_type.dump()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::{SymbolKind, SymbolTable};
+
+    static SOURCE: &str = "import { log as print } from \"./io.sk\"
+const LIMIT = 10
+fn main() {
+    let a = helper(LIMIT)
+    if a {
+        let a = 2
+        print(a)
+    }
+    print(a)
+}
+fn helper(x: int): int = x";
+
+    fn names(table: &SymbolTable, offset: usize) -> Vec<String> {
+        table
+            .symbols_in_scope(offset)
+            .into_iter()
+            .map(|id| table.symbol(id).name.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn definitions_and_references() {
+        let table = SymbolTable::build(&parse(SOURCE).unwrap());
+        let helper_call = SOURCE.find("helper(").unwrap();
+        let helper = table.definition_at(helper_call + 2).unwrap();
+        assert_eq!(table.symbol(helper).kind, SymbolKind::Function);
+        assert_eq!(
+            table.symbol(helper).detail.as_deref(),
+            Some("fn helper(x: int): int")
+        );
+        assert_eq!(
+            table.symbol(helper).name.span.start,
+            SOURCE.rfind("helper").unwrap()
+        );
+        assert_eq!(
+            table
+                .references_of(helper)
+                .map(|r| r.span.start)
+                .collect::<Vec<_>>(),
+            vec![helper_call]
+        );
+
+        // The inner `a` shadows the outer one inside the `if` only
+        let print_inner = SOURCE.find("print(a)").unwrap() + 6;
+        let print_outer = SOURCE.rfind("print(a)").unwrap() + 6;
+        let inner = table.definition_at(print_inner).unwrap();
+        let outer = table.definition_at(print_outer).unwrap();
+        assert_ne!(inner, outer);
+        assert_eq!(table.references_of(outer).count(), 2);
+
+        let print = table.definition_at(SOURCE.find("print").unwrap()).unwrap();
+        assert_eq!(table.symbol(print).kind, SymbolKind::Import);
+        assert_eq!(table.references_of(print).count(), 2);
+    }
+
+    #[test]
+    fn symbols_in_scope() {
+        let table = SymbolTable::build(&parse(SOURCE).unwrap());
+        assert_eq!(
+            names(&table, SOURCE.find("print(a)").unwrap()),
+            vec!["a", "LIMIT", "print", "helper", "main"]
+        );
+        assert_eq!(
+            names(&table, SOURCE.len()),
+            vec!["x", "LIMIT", "print", "helper", "main"]
+        );
+        // Variables can't be used before they are declared, functions can
+        assert_eq!(
+            names(&table, SOURCE.find("let a").unwrap()),
+            vec!["LIMIT", "print", "helper", "main"]
+        );
+    }
+
+    #[test]
+    fn unresolved_names() {
+        let source = "fn f(): int = later + missing\nlet later = 1\nundefined(later)";
+        let table = SymbolTable::build(&parse(source).unwrap());
+        assert_eq!(
+            table
+                .unresolved()
+                .map(|n| n.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["missing", "undefined"]
+        );
+        let table = SymbolTable::build(&parse(SOURCE).unwrap());
+        assert_eq!(table.unresolved().count(), 0);
+    }
+
+    #[test]
+    fn document_symbols() {
+        let table = SymbolTable::build(&parse(SOURCE).unwrap());
+        let outline = table
+            .document_symbols()
+            .into_iter()
+            .map(|s| {
+                let children = s
+                    .children
+                    .iter()
+                    .map(|c| table.symbol(c.symbol).name.name.as_str())
+                    .collect::<Vec<_>>();
+                format!("{} {:?}", table.symbol(s.symbol).name.name, children)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outline,
+            vec!["print []", "LIMIT []", "main [\"a\", \"a\"]", "helper []",]
+        );
+    }
+}
@@ -0,0 +1,325 @@
+//! Warnings about code that has no effect on the program: unused local
+//! bindings and parameters, functions never called from `main`
+//! and statements that can't be reached.
+
+use crate::{
+    analyzer::symbols::{SymbolId, SymbolKind, SymbolTable},
+    error::Diagnostic,
+    parser::ast::{Block, CatchClause, Expr, IfBranch, Module, Stmt},
+};
+
+pub fn check(module: &Module) -> Vec<Diagnostic> {
+    let table = SymbolTable::build(module);
+    let mut diagnostics = Vec::new();
+    report_unreachable_code(&module.statements, &mut diagnostics);
+    report_unreachable_functions(&table, &mut diagnostics);
+    report_unused(&table, &mut diagnostics);
+    diagnostics.sort_by_key(|d| d.span.start);
+    diagnostics
+}
+
+fn report_unreachable_code(body: &[Stmt], diagnostics: &mut Vec<Diagnostic>) {
+    let mut reported = false;
+    for (i, stmt) in body.iter().enumerate() {
+        match stmt {
+            Stmt::Function { body, .. } => report_unreachable_code(&body.statements, diagnostics),
+            Stmt::Var { value, .. } | Stmt::Const { value, .. } | Stmt::Expr(value) => {
+                report_unreachable_in_expr(value, diagnostics)
+            }
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                report_unreachable_code(&body.statements, diagnostics);
+                if let Some(CatchClause { body, .. }) = catch {
+                    report_unreachable_code(&body.statements, diagnostics);
+                }
+                if let Some(Block { statements, .. }) = finally {
+                    report_unreachable_code(statements, diagnostics);
+                }
+            }
+            Stmt::Throw { value, .. } => report_unreachable_in_expr(value, diagnostics),
+            Stmt::Import { .. } | Stmt::ExternFunction { .. } => {}
+        }
+        if !reported && i + 1 < body.len() && always_throws(stmt) {
+            reported = true;
+            let (keyword, span) = match stmt {
+                Stmt::Throw { span, .. } => ("throw", *span),
+                Stmt::Try { body, .. } => ("try", body.span),
+                Stmt::Expr(Expr::If { branches, .. }) => ("if", branches[0].body.span),
+                _ => unreachable!("only `throw`, `try` and `if` always throw"),
+            };
+            diagnostics.push(Diagnostic::warning(
+                format!("any code following this `{}` is unreachable", keyword),
+                span,
+            ));
+        }
+    }
+}
+
+// Whether the statement never completes normally: a `throw`, an `if`
+// with an `else` whose every branch throws, or a `try` whose `finally`
+// throws or whose body and `catch` both throw
+fn always_throws(stmt: &Stmt) -> bool {
+    let block_throws = |block: &Block| block.statements.iter().any(always_throws);
+    match stmt {
+        Stmt::Throw { .. } => true,
+        Stmt::Expr(Expr::If {
+            branches,
+            else_body: Some(else_body),
+        }) => branches.iter().all(|b| block_throws(&b.body)) && block_throws(else_body),
+        Stmt::Try {
+            body,
+            catch,
+            finally,
+        } => {
+            finally.as_ref().is_some_and(block_throws)
+                || (block_throws(body) && catch.as_ref().is_none_or(|c| block_throws(&c.body)))
+        }
+        _ => false,
+    }
+}
+
+fn report_unreachable_in_expr(expr: &Expr, diagnostics: &mut Vec<Diagnostic>) {
+    match expr {
+        Expr::Integer(_) | Expr::Float(_) | Expr::String(_) | Expr::Ident(_) | Expr::Error(_) => {}
+        Expr::BinaryOp { left, right, .. } => {
+            report_unreachable_in_expr(left, diagnostics);
+            report_unreachable_in_expr(right, diagnostics);
+        }
+        Expr::Call { target, arguments } => {
+            report_unreachable_in_expr(target, diagnostics);
+            for arg in arguments {
+                report_unreachable_in_expr(&arg.expr, diagnostics);
+            }
+        }
+        Expr::BracketAccess { target, expr } => {
+            report_unreachable_in_expr(target, diagnostics);
+            report_unreachable_in_expr(expr, diagnostics);
+        }
+        Expr::DotAccess { target: expr, .. } | Expr::Await(expr) | Expr::Cast { expr, .. } => {
+            report_unreachable_in_expr(expr, diagnostics)
+        }
+        Expr::If {
+            branches,
+            else_body,
+        } => {
+            for IfBranch { condition, body } in branches {
+                report_unreachable_in_expr(condition, diagnostics);
+                report_unreachable_code(&body.statements, diagnostics);
+            }
+            if let Some(body) = else_body {
+                report_unreachable_code(&body.statements, diagnostics);
+            }
+        }
+    }
+}
+
+fn is_top_level_function(table: &SymbolTable, id: SymbolId) -> bool {
+    table.symbol(id).kind == SymbolKind::Function && table.is_top_level(id)
+}
+
+// Top-level function the code of `container` belongs to,
+// `None` for statements at module level
+fn owner(table: &SymbolTable, mut container: Option<SymbolId>) -> Option<SymbolId> {
+    while let Some(id) = container {
+        if is_top_level_function(table, id) {
+            return Some(id);
+        }
+        container = table.symbol(id).container;
+    }
+    None
+}
+
+// Only programs with a `main` function have an entry point, without one
+// the module is a library and all of its functions may be imported
+fn report_unreachable_functions(table: &SymbolTable, diagnostics: &mut Vec<Diagnostic>) {
+    let top_level = table
+        .symbols()
+        .map(|(id, _)| id)
+        .filter(|&id| is_top_level_function(table, id))
+        .collect::<Vec<_>>();
+    let Some(main) = top_level
+        .iter()
+        .copied()
+        .find(|&id| table.symbol(id).name.name == "main")
+    else {
+        return;
+    };
+
+    // References from a top-level function (or the module) to top-level functions
+    let calls = top_level
+        .iter()
+        .flat_map(|&to| {
+            table
+                .references_of(to)
+                .map(move |r| (owner(table, r.container), to))
+        })
+        .collect::<Vec<_>>();
+
+    let mut reachable = vec![main];
+    reachable.extend(
+        calls
+            .iter()
+            .filter(|(from, _)| from.is_none())
+            .map(|(_, to)| *to),
+    );
+    let mut i = 0;
+    while i < reachable.len() {
+        let from = Some(reachable[i]);
+        for (_, to) in calls.iter().filter(|(f, _)| *f == from) {
+            if !reachable.contains(to) {
+                reachable.push(*to);
+            }
+        }
+        i += 1;
+    }
+
+    for &id in &top_level {
+        if !reachable.contains(&id) {
+            let name = &table.symbol(id).name;
+            diagnostics.push(Diagnostic::warning(
+                format!("function `{}` is never called", name.name),
+                name.span,
+            ));
+        }
+    }
+}
+
+// Top-level variables may be imported by other modules and top-level
+// functions are checked for reachability instead. Extern functions,
+// imports and catch bindings are never reported.
+fn report_unused(table: &SymbolTable, diagnostics: &mut Vec<Diagnostic>) {
+    for (id, symbol) in table.symbols() {
+        if table.references_of(id).next().is_some() {
+            continue;
+        }
+        let name = &symbol.name.name;
+        let message = match symbol.kind {
+            SymbolKind::Variable if !table.is_top_level(id) => {
+                format!("unused variable `{}`", name)
+            }
+            SymbolKind::Constant if !table.is_top_level(id) => {
+                format!("unused constant `{}`", name)
+            }
+            SymbolKind::Parameter => format!("unused parameter `{}`", name),
+            SymbolKind::Function if !table.is_top_level(id) => {
+                format!("function `{}` is never used", name)
+            }
+            _ => continue,
+        };
+        diagnostics.push(Diagnostic::warning(message, symbol.name.span));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::check;
+
+    fn warnings(source: &str) -> Vec<String> {
+        check(&parse(source).expect("source should parse"))
+            .into_iter()
+            .map(|d| d.render(source))
+            .collect()
+    }
+
+    #[test]
+    fn unused_locals_and_params() {
+        assert_eq!(
+            warnings("let top = 1\nfn f(a: int, b: int): int {\n    let c = a\n    let d = c\n    fn g() {}\n    a\n}"),
+            vec![
+                "warning at 2:14: unused parameter `b`",
+                "warning at 4:9: unused variable `d`",
+                "warning at 5:8: function `g` is never used",
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_functions_use_later_bindings() {
+        assert!(warnings(
+            "fn main() = outer()\nfn outer() {\n    fn inner() = x\n    let x = 1\n    inner()\n}"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn shadowed_binding_is_unused() {
+        assert_eq!(
+            warnings("fn f(): int {\n    let a = 1\n    let a = 2\n    a\n}"),
+            vec!["warning at 2:9: unused variable `a`"]
+        );
+    }
+
+    #[test]
+    fn functions_unreachable_from_main() {
+        assert_eq!(
+            warnings("fn main() = a()\nfn a() = b()\nfn b() {}\nfn c() = d()\nfn d() = c()\nfn e() {}\ne()"),
+            vec![
+                "warning at 4:4: function `c` is never called",
+                "warning at 5:4: function `d` is never called",
+            ]
+        );
+        // Libraries have no entry point
+        assert!(warnings("fn a() {}").is_empty());
+    }
+
+    #[test]
+    fn destructured_bindings() {
+        assert_eq!(
+            warnings("fn f(p: int): int {\n    let (a, { x, y: b }) = p\n    a + x\n}"),
+            vec!["warning at 2:21: unused variable `b`"]
+        );
+    }
+
+    #[test]
+    fn code_after_throw() {
+        assert_eq!(
+            warnings("fn f() {\n    throw \"boom\"\n    g()\n    h()\n}"),
+            vec!["warning at 2:5: any code following this `throw` is unreachable"]
+        );
+        assert!(warnings("fn f() {\n    try { throw 1 } catch (e) {}\n    g()\n}").is_empty());
+    }
+
+    #[test]
+    fn code_after_statements_which_always_throw() {
+        assert_eq!(
+            warnings("fn f(a: int) {\n    if a { throw 1 } else if b { throw 2 } else { throw 3 }\n    g()\n}"),
+            vec!["warning at 2:10: any code following this `if` is unreachable"]
+        );
+        assert_eq!(
+            warnings("fn f() {\n    try { g() } finally { throw 1 }\n    g()\n}\nfn h() {\n    try { throw 1 } catch (e) { throw e }\n    g()\n}"),
+            vec![
+                "warning at 2:9: any code following this `try` is unreachable",
+                "warning at 6:9: any code following this `try` is unreachable",
+            ]
+        );
+        assert!(warnings("fn f(a: int) {\n    if a { throw 1 }\n    g()\n}").is_empty());
+        assert_eq!(
+            warnings("fn f() {\n    try { throw 1 } finally { g() }\n    g()\n}"),
+            vec!["warning at 2:9: any code following this `try` is unreachable"]
+        );
+    }
+
+    #[test]
+    fn unused_local_constant() {
+        assert_eq!(
+            warnings("const TOP = 1\nfn f() {\n    const LIMIT = 2\n}"),
+            vec!["warning at 3:11: unused constant `LIMIT`"]
+        );
+    }
+
+    #[test]
+    fn if_branches_are_scopes() {
+        assert_eq!(
+            warnings("fn f(a: int, b: int): int {\n    if a {\n        let c = 1\n        b\n    } else if b {\n        throw a\n        a\n    } else {\n        0\n    }\n}"),
+            vec![
+                "warning at 3:13: unused variable `c`",
+                "warning at 6:9: any code following this `throw` is unreachable",
+            ]
+        );
+    }
+}
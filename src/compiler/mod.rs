@@ -1,6 +1,10 @@
-use crate::parser::ast::{BinaryOpKind, Expr, ImportedSymbol, Module, Stmt};
+use crate::analyzer::numeric::NumType;
+use crate::parser::ast::{
+    pattern::Pattern, BinaryOpKind, Block, CallArgument, CatchClause, Expr, FunctionParam, Ident,
+    IfBranch, ImportedSymbol, Module, Stmt, TypeUsage,
+};
 
-static SPACE: &'static str = "  ";
+static SPACE: &str = "  ";
 
 pub fn gen(module: Module) -> String {
     let mut buf = String::new();
@@ -11,26 +15,86 @@ pub fn gen(module: Module) -> String {
     buf
 }
 
+fn gen_indent(buf: &mut String, deep: usize) {
+    buf.push_str(SPACE.repeat(deep).as_str());
+}
+
 fn gen_stmt(buf: &mut String, deep: usize, stmt: Stmt) {
+    // Extern functions are provided by the host environment,
+    // so there is nothing to emit for them
+    if let Stmt::ExternFunction { .. } = stmt {
+        return;
+    }
+    gen_indent(buf, deep);
+    // Any top-level definition may be imported by another module
+    if deep == 0 {
+        if let Stmt::Var { .. } | Stmt::Const { .. } | Stmt::Function { .. } = stmt {
+            buf.push_str("export ");
+        }
+    }
     match stmt {
-        Stmt::Import { symbols, path } => gen_import(buf, deep, symbols, path),
+        Stmt::Import {
+            symbols,
+            namespace,
+            path,
+            is_pub,
+        } => gen_import(buf, deep, symbols, namespace, path, is_pub),
         Stmt::Var {
-            name,
+            pattern,
             is_mut,
             value,
-        } => gen_var(buf, deep, name, is_mut, value),
-        Stmt::Const { name, value } => gen_var(buf, deep, name, false, value),
+        } => gen_var(buf, deep, gen_pattern(pattern), is_mut, value),
+        Stmt::Const { name, value } => gen_var(buf, deep, name.name, false, value),
         Stmt::Function {
             name,
+            is_async,
             params,
             ret_type,
             body,
-        } => todo!(),
-        Stmt::Expr(expr) => gen_expr(buf, deep, expr),
+        } => gen_function(buf, deep, name.name, is_async, params, ret_type, body),
+        Stmt::ExternFunction { .. } => {}
+        Stmt::Try {
+            body,
+            catch,
+            finally,
+        } => gen_try(buf, deep, body, catch, finally),
+        Stmt::Throw { value, .. } => {
+            buf.push_str("throw ");
+            gen_expr(buf, deep, value);
+            buf.push_str(";\n");
+        }
+        Stmt::Expr(Expr::If {
+            branches,
+            else_body,
+        }) => gen_if(buf, deep, branches, else_body, false),
+        Stmt::Expr(expr) => {
+            gen_expr(buf, deep, expr);
+            buf.push_str(";\n");
+        }
     }
 }
 
-fn gen_import(buf: &mut String, deep: usize, symbols: Vec<ImportedSymbol>, path: String) {
+fn gen_import(
+    buf: &mut String,
+    deep: usize,
+    symbols: Vec<ImportedSymbol>,
+    namespace: Option<Ident>,
+    path: String,
+    is_pub: bool,
+) {
+    if let Some(namespace) = namespace {
+        buf.push_str("import * as ");
+        buf.push_str(&namespace.name);
+        buf.push_str(" from ");
+        gen_string(buf, path);
+        buf.push_str(";\n");
+        if is_pub {
+            gen_indent(buf, deep);
+            buf.push_str(format!("export {{ {} }};\n", namespace.name).as_str());
+        }
+        return;
+    }
+
     buf.push_str("import {");
     let s = symbols.iter().map(gen_sym).collect::<Vec<String>>();
     if s.len() > 3 {
@@ -47,6 +111,16 @@ fn gen_import(buf: &mut String, deep: usize, symbols: Vec<ImportedSymbol>, path:
     buf.push_str("} from ");
     gen_string(buf, path);
     buf.push_str(";\n");
+
+    // Re-exported under the local name, the one other modules see
+    if is_pub {
+        let names = symbols
+            .iter()
+            .map(|sym| sym.imported_as.as_ref().unwrap_or(&sym.name).as_str())
+            .collect::<Vec<_>>();
+        gen_indent(buf, deep);
+        buf.push_str(format!("export {{ {} }};\n", names.join(", ")).as_str());
+    }
 }
 
 fn gen_sym(sym: &ImportedSymbol) -> String {
@@ -65,30 +139,313 @@ fn gen_var(buf: &mut String, deep: usize, name: String, is_mut: bool, expr: Expr
     buf.push(' ');
     buf.push_str(&name);
     buf.push_str(" = ");
-    gen_expr(buf, deep + 1, expr);
+    gen_expr(buf, deep, expr);
     buf.push_str(";\n")
 }
 
+// Destructuring target of `let`, `let` only accepts
+// patterns which bind every part they match
+fn gen_pattern(pattern: Pattern) -> String {
+    match pattern {
+        Pattern::Binding(name) => name.name,
+        Pattern::Tuple(items) => {
+            let items = items.into_iter().map(gen_pattern).collect::<Vec<_>>();
+            format!("[{}]", items.join(", "))
+        }
+        Pattern::Struct { fields, .. } => {
+            let fields = fields
+                .into_iter()
+                .map(|field| match field.pattern {
+                    Pattern::Binding(name) if name.name == field.name => name.name,
+                    pattern => format!("{}: {}", field.name, gen_pattern(pattern)),
+                })
+                .collect::<Vec<_>>();
+            format!("{{ {} }}", fields.join(", "))
+        }
+        Pattern::Integer(_) | Pattern::Float(_) | Pattern::String(_) => {
+            unreachable!("literal patterns can't be bound with `let`")
+        }
+    }
+}
+
+fn gen_function(
+    buf: &mut String,
+    deep: usize,
+    name: String,
+    is_async: bool,
+    params: Vec<FunctionParam>,
+    ret_type: TypeUsage,
+    body: Block,
+) {
+    if is_async {
+        buf.push_str("async ");
+    }
+    buf.push_str("function ");
+    buf.push_str(&name);
+    buf.push('(');
+    let p = params
+        .iter()
+        .map(|p| p.name.name.as_str())
+        .collect::<Vec<&str>>();
+    buf.push_str(p.join(", ").as_str());
+    buf.push_str(") {");
+    if body.statements.is_empty() {
+        buf.push_str("}\n");
+        return;
+    }
+    buf.push('\n');
+
+    // The trailing expression of a function with a non-Unit
+    // return type is its result
+    gen_body(buf, deep + 1, body.statements, ret_type.name != "Unit");
+    gen_indent(buf, deep);
+    buf.push_str("}\n");
+}
+
+// Statements of a body, with `returns` the trailing expression is returned
+fn gen_body(buf: &mut String, deep: usize, body: Vec<Stmt>, returns: bool) {
+    let last = body.len().saturating_sub(1);
+    for (i, stmt) in body.into_iter().enumerate() {
+        match stmt {
+            Stmt::Expr(Expr::If {
+                branches,
+                else_body,
+            }) if returns && i == last => {
+                gen_indent(buf, deep);
+                gen_if(buf, deep, branches, else_body, true);
+            }
+            Stmt::Expr(expr) if returns && i == last => gen_return(buf, deep, expr),
+            stmt => gen_stmt(buf, deep, stmt),
+        }
+    }
+}
+
+fn gen_if(
+    buf: &mut String,
+    deep: usize,
+    branches: Vec<IfBranch>,
+    else_body: Option<Block>,
+    returns: bool,
+) {
+    for (i, IfBranch { condition, body }) in branches.into_iter().enumerate() {
+        if i > 0 {
+            buf.push_str(" else ");
+        }
+        buf.push_str("if (");
+        gen_expr(buf, deep, condition);
+        buf.push_str(") ");
+        gen_block(buf, deep, body, returns);
+    }
+    if let Some(body) = else_body {
+        buf.push_str(" else ");
+        gen_block(buf, deep, body, returns);
+    }
+    buf.push('\n');
+}
+
+// `if` used as a value. Branches with a single expression become a
+// conditional expression, anything else is wrapped into a function.
+fn gen_if_expr(buf: &mut String, deep: usize, branches: Vec<IfBranch>, else_body: Option<Block>) {
+    let is_simple = |body: &[Stmt]| matches!(body, [Stmt::Expr(_)]);
+    if branches.iter().all(|b| is_simple(&b.body.statements))
+        && else_body.as_ref().is_none_or(|b| is_simple(&b.statements))
+    {
+        for IfBranch { condition, body } in branches {
+            gen_target(buf, deep, condition);
+            buf.push_str(" ? ");
+            if let Some(Stmt::Expr(value)) = body.statements.into_iter().next() {
+                gen_target(buf, deep, value);
+            }
+            buf.push_str(" : ");
+        }
+        match else_body.and_then(|body| body.statements.into_iter().next()) {
+            Some(Stmt::Expr(value)) => gen_target(buf, deep, value),
+            _ => buf.push_str("undefined"),
+        }
+        return;
+    }
+
+    let branches_await = branches
+        .iter()
+        .any(|b| contains_await(&b.condition) || body_contains_await(&b.body.statements));
+    if branches_await
+        || else_body
+            .as_ref()
+            .is_some_and(|b| body_contains_await(&b.statements))
+    {
+        buf.push_str("await (async () => {\n");
+    } else {
+        buf.push_str("(() => {\n");
+    }
+    gen_indent(buf, deep + 1);
+    gen_if(buf, deep + 1, branches, else_body, true);
+    gen_indent(buf, deep);
+    buf.push_str("})()");
+}
+
+// Whether the expression awaits, functions defined inside are not looked into
+fn contains_await(expr: &Expr) -> bool {
+    match expr {
+        Expr::Integer(_) | Expr::Float(_) | Expr::String(_) | Expr::Ident(_) | Expr::Error(_) => {
+            false
+        }
+        Expr::Await(_) => true,
+        Expr::BinaryOp { left, right, .. } => contains_await(left) || contains_await(right),
+        Expr::Call { target, arguments } => {
+            contains_await(target) || arguments.iter().any(|arg| contains_await(&arg.expr))
+        }
+        Expr::BracketAccess { target, expr } => contains_await(target) || contains_await(expr),
+        Expr::DotAccess { target: expr, .. } | Expr::Cast { expr, .. } => contains_await(expr),
+        Expr::If {
+            branches,
+            else_body,
+        } => {
+            branches
+                .iter()
+                .any(|b| contains_await(&b.condition) || body_contains_await(&b.body.statements))
+                || else_body
+                    .as_ref()
+                    .is_some_and(|b| body_contains_await(&b.statements))
+        }
+    }
+}
+
+fn body_contains_await(body: &[Stmt]) -> bool {
+    body.iter().any(|stmt| match stmt {
+        Stmt::Var { value, .. } | Stmt::Const { value, .. } => contains_await(value),
+        Stmt::Throw { value, .. } | Stmt::Expr(value) => contains_await(value),
+        Stmt::Try {
+            body,
+            catch,
+            finally,
+        } => {
+            body_contains_await(&body.statements)
+                || catch
+                    .as_ref()
+                    .is_some_and(|c| body_contains_await(&c.body.statements))
+                || finally
+                    .as_ref()
+                    .is_some_and(|b| body_contains_await(&b.statements))
+        }
+        Stmt::Import { .. } | Stmt::Function { .. } | Stmt::ExternFunction { .. } => false,
+    })
+}
+
+fn gen_try(
+    buf: &mut String,
+    deep: usize,
+    body: Block,
+    catch: Option<CatchClause>,
+    finally: Option<Block>,
+) {
+    buf.push_str("try ");
+    gen_block(buf, deep, body, false);
+    if let Some(catch) = catch {
+        buf.push_str(" catch ");
+        if let Some(binding) = catch.binding {
+            buf.push('(');
+            buf.push_str(binding.name.as_str());
+            buf.push_str(") ");
+        }
+        gen_block(buf, deep, catch.body, false);
+    }
+    if let Some(finally) = finally {
+        buf.push_str(" finally ");
+        gen_block(buf, deep, finally, false);
+    }
+    buf.push('\n');
+}
+
+fn gen_block(buf: &mut String, deep: usize, body: Block, returns: bool) {
+    buf.push('{');
+    if body.statements.is_empty() {
+        buf.push('}');
+        return;
+    }
+    buf.push('\n');
+    gen_body(buf, deep + 1, body.statements, returns);
+    gen_indent(buf, deep);
+    buf.push('}');
+}
+
+fn gen_return(buf: &mut String, deep: usize, expr: Expr) {
+    gen_indent(buf, deep);
+    buf.push_str("return ");
+    gen_expr(buf, deep, expr);
+    buf.push_str(";\n");
+}
+
 fn gen_expr(buf: &mut String, deep: usize, expr: Expr) {
     match expr {
         Expr::Integer(i) => gen_int(buf, i),
         Expr::Float(f) => gen_float(buf, f),
         Expr::String(s) => gen_string(buf, s),
-        Expr::Ident(i) => buf.push_str(i.as_str()),
-        Expr::BinaryOp {
-            kind,
-            left,
-            right,
-        } => gen_bin_op(buf, deep, kind, left, right),
-        Expr::Call {
-            target: _,
-            arguments: _,
-        } => todo!(),
-        Expr::DotAccess { target, name } => todo!(),
-        Expr::BracketAccess { target, expr } => todo!(),
+        Expr::Ident(i) => buf.push_str(i.name.as_str()),
+        Expr::BinaryOp { kind, left, right } => gen_bin_op(buf, deep, kind, *left, *right),
+        Expr::Call { target, arguments } => gen_call(buf, deep, *target, arguments),
+        Expr::DotAccess { target, name } => {
+            gen_target(buf, deep, *target);
+            buf.push('.');
+            buf.push_str(name.name.as_str());
+        }
+        Expr::BracketAccess { target, expr } => {
+            gen_target(buf, deep, *target);
+            buf.push('[');
+            gen_expr(buf, deep, *expr);
+            buf.push(']');
+        }
+        Expr::Await(expr) => {
+            buf.push_str("await ");
+            gen_target(buf, deep, *expr);
+        }
+        Expr::Cast { expr, r#type } => gen_cast(buf, deep, *expr, r#type),
+        Expr::If {
+            branches,
+            else_body,
+        } => gen_if_expr(buf, deep, branches, else_body),
+        // Modules with syntax errors are never compiled
+        Expr::Error(_) => buf.push_str("undefined"),
     }
 }
 
+// JavaScript only has doubles, the conversions truncate and wrap them
+// the same way the target type would. 64 bit integers are only truncated.
+fn gen_cast(buf: &mut String, deep: usize, expr: Expr, r#type: TypeUsage) {
+    let (before, after) = match NumType::from_usage(&r#type) {
+        Some(NumType::I32) => {
+            gen_bitwise_cast(buf, deep, expr, " | 0)");
+            return;
+        }
+        Some(NumType::U32) => {
+            gen_bitwise_cast(buf, deep, expr, " >>> 0)");
+            return;
+        }
+        Some(NumType::I64 | NumType::U64) => ("Math.trunc(", ")"),
+        Some(NumType::F32) => ("Math.fround(", ")"),
+        Some(NumType::F64) => ("Number(", ")"),
+        // Casts to other types are rejected by the analyzer
+        None => {
+            gen_expr(buf, deep, expr);
+            return;
+        }
+    };
+    buf.push_str(before);
+    gen_expr(buf, deep, expr);
+    buf.push_str(after);
+}
+
+// The conditional operator binds looser than `|` and `>>>`,
+// so a conditional operand is wrapped in parentheses
+fn gen_bitwise_cast(buf: &mut String, deep: usize, expr: Expr, after: &str) {
+    buf.push('(');
+    if let Expr::If { .. } = expr {
+        gen_target(buf, deep, expr);
+    } else {
+        gen_expr(buf, deep, expr);
+    }
+    buf.push_str(after);
+}
+
 fn gen_int(buf: &mut String, i: i32) {
     buf.push_str(i.to_string().as_str())
 }
@@ -103,10 +460,303 @@ fn gen_string(buf: &mut String, string: String) {
     buf.push('"');
 }
 
-fn gen_bin_op(buf: &mut String, deep: usize, op: BinaryOpKind, left: Box<Expr>, right: Box<Expr>) {
-    gen_expr(buf, deep, left.as_ref().clone());
+fn gen_bin_op(buf: &mut String, deep: usize, op: BinaryOpKind, left: Expr, right: Expr) {
+    gen_operand(buf, deep, &op, left, false);
     buf.push(' ');
     buf.push_str(op.to_op());
     buf.push(' ');
-    gen_expr(buf, deep, right.as_ref().clone());
+    gen_operand(buf, deep, &op, right, true);
+}
+
+// Operands are parenthesized when they bind looser than the operator,
+// the right one also when it binds equally, as all operators are left-associative
+fn gen_operand(buf: &mut String, deep: usize, op: &BinaryOpKind, expr: Expr, is_right: bool) {
+    let wrap = match &expr {
+        Expr::BinaryOp { kind, .. } => {
+            kind.precedence() < op.precedence()
+                || (is_right && kind.precedence() == op.precedence())
+        }
+        Expr::If { .. } => true,
+        _ => false,
+    };
+    if wrap {
+        buf.push('(');
+        gen_expr(buf, deep, expr);
+        buf.push(')');
+    } else {
+        gen_expr(buf, deep, expr);
+    }
+}
+
+// Target of a call or member access, also used for the parts of a conditional expression
+fn gen_target(buf: &mut String, deep: usize, target: Expr) {
+    if let Expr::BinaryOp { .. } | Expr::If { .. } | Expr::Await(_) = target {
+        buf.push('(');
+        gen_expr(buf, deep, target);
+        buf.push(')');
+    } else {
+        gen_expr(buf, deep, target);
+    }
+}
+
+// JavaScript has no named arguments, so every argument is passed
+// positionally in the order it was written
+fn gen_call(buf: &mut String, deep: usize, target: Expr, arguments: Vec<CallArgument>) {
+    gen_target(buf, deep, target);
+    buf.push('(');
+    for (i, arg) in arguments.into_iter().enumerate() {
+        if i > 0 {
+            buf.push_str(", ");
+        }
+        gen_expr(buf, deep, arg.expr);
+    }
+    buf.push(')');
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analyzer::{consts::ImportedConsts, numeric::truncate_integer_division},
+        parser::parse,
+    };
+
+    use super::gen;
+
+    fn gen_js(source: &str) -> String {
+        gen(parse(source).expect("source should parse"))
+    }
+
+    #[test]
+    fn vars() {
+        assert_eq!(
+            gen_js("let a = 1\nlet mut b = 2.5\nconst c = \"str\""),
+            "export const a = 1;\nexport let b = 2.5;\nexport const c = \"str\";\n"
+        )
+    }
+
+    #[test]
+    fn destructuring() {
+        assert_eq!(
+            gen_js("let (a, { x, y: (b, c) }) = pair\nlet mut { z } = point"),
+            "export const [a, { x, y: [b, c] }] = pair;\nexport let { z } = point;\n"
+        )
+    }
+
+    #[test]
+    fn imports() {
+        assert_eq!(
+            gen_js(r#"import { a as b, c } from "./mod.sk""#),
+            "import { a as b, c } from \"./mod.sk\";\n"
+        );
+        assert_eq!(
+            gen_js(r#"import { a, b, c, d } from "./mod.sk""#),
+            "import {\n  a,\n  b,\n  c,\n  d\n} from \"./mod.sk\";\n"
+        );
+        assert_eq!(
+            gen_js("import * as m from \"./mod.sk\"\npub import { a as b, c } from \"./mod.sk\""),
+            "import * as m from \"./mod.sk\";\nimport { a as b, c } from \"./mod.sk\";\nexport { b, c };\n"
+        );
+    }
+
+    #[test]
+    fn binary_ops_keep_grouping() {
+        assert_eq!(
+            gen_js("let a = (1 + 2) * 3 - (4 - 5) % b"),
+            "export const a = (1 + 2) * 3 - (4 - 5) % b;\n"
+        )
+    }
+
+    #[test]
+    fn calls_and_access() {
+        assert_eq!(
+            gen_js("console.log(items[0], size = (a + b).len)"),
+            "console.log(items[0], (a + b).len);\n"
+        )
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(
+            gen_js(
+                "fn add(a: int, b: int): int {\n    let c = a + b\n    c\n}\nfn noop() {}\nfn hello() = print(\"hi\")"
+            ),
+            [
+                "export function add(a, b) {",
+                "  const c = a + b;",
+                "  return c;",
+                "}",
+                "export function noop() {}",
+                "export function hello() {",
+                "  print(\"hi\");",
+                "}",
+                "",
+            ]
+            .join("\n")
+        )
+    }
+
+    #[test]
+    fn async_functions() {
+        assert_eq!(
+            gen_js("async fn load(url: string): string {\n    let res = await fetch(url)\n    await res.text()\n}"),
+            [
+                "export async function load(url) {",
+                "  const res = await fetch(url);",
+                "  return await res.text();",
+                "}",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            gen_js("async fn f() {\n    (await p).x\n    (await load)(1)\n}"),
+            "export async function f() {\n  (await p).x;\n  (await load)(1);\n}\n"
+        );
+    }
+
+    #[test]
+    fn exceptions() {
+        assert_eq!(
+            gen_js("fn main() {\n    try {\n        throw \"boom\"\n    } catch (e) {\n        log(e)\n    } finally {}\n    try {} catch {}\n}"),
+            [
+                "export function main() {",
+                "  try {",
+                "    throw \"boom\";",
+                "  } catch (e) {",
+                "    log(e);",
+                "  } finally {}",
+                "  try {} catch {}",
+                "}",
+                "",
+            ]
+            .join("\n")
+        )
+    }
+
+    #[test]
+    fn casts() {
+        assert_eq!(
+            gen_js("let a = x as i32 + (y + 1) as u32 * z as f32\nlet b = x as i64 as f64"),
+            [
+                "export const a = (x | 0) + (y + 1 >>> 0) * Math.fround(z);",
+                "export const b = Number(Math.trunc(x));",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            gen_js("let a = if x { 1.5 } else { 2.5 } as i32\nlet b = if x { 1 } else { 2 } as u32\nlet c = if x { 1 } else { 2 } as i64"),
+            [
+                "export const a = ((x ? 1.5 : 2.5) | 0);",
+                "export const b = ((x ? 1 : 2) >>> 0);",
+                "export const c = Math.trunc(x ? 1 : 2);",
+                "",
+            ]
+            .join("\n")
+        )
+    }
+
+    #[test]
+    fn integer_division() {
+        let mut module = parse(
+            "const A = 7 / 2\nconst B = 7.0 / 2\nfn g(a: i32, b: i32): i32 = a / b\nfn h(a: u64, b: u32, c: f32) {\n    a / b + c / 2\n}",
+        )
+        .unwrap();
+        truncate_integer_division(&mut module, &ImportedConsts::new());
+        assert_eq!(
+            gen(module),
+            [
+                "export const A = (7 / 2 | 0);",
+                "export const B = 7 / 2;",
+                "export function g(a, b) {",
+                "  return (a / b | 0);",
+                "}",
+                "export function h(a, b, c) {",
+                "  Math.trunc(a / b) + c / 2;",
+                "}",
+                "",
+            ]
+            .join("\n")
+        )
+    }
+
+    #[test]
+    fn if_chains() {
+        assert_eq!(
+            gen_js("fn sign(x: int): int {\n    if x {\n        log(x)\n        1\n    } else if y { 2 } else { 0 }\n}\nif a { f() }"),
+            [
+                "export function sign(x) {",
+                "  if (x) {",
+                "    log(x);",
+                "    return 1;",
+                "  } else if (y) {",
+                "    return 2;",
+                "  } else {",
+                "    return 0;",
+                "  }",
+                "}",
+                "if (a) {",
+                "  f();",
+                "}",
+                "",
+            ]
+            .join("\n")
+        )
+    }
+
+    #[test]
+    fn if_expressions() {
+        assert_eq!(
+            gen_js("let a = if x { 1 } else if y { b + 1 } else { 3 } * 2\nlet b = if x { 1 }"),
+            [
+                "export const a = (x ? 1 : y ? (b + 1) : 3) * 2;",
+                "export const b = x ? 1 : undefined;",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            gen_js("async fn f(): int {\n    let a = if x {\n        let t = await g()\n        t\n    } else { 0 }\n    a\n}"),
+            [
+                "export async function f() {",
+                "  const a = await (async () => {",
+                "    if (x) {",
+                "      const t = await g();",
+                "      return t;",
+                "    } else {",
+                "      return 0;",
+                "    }",
+                "  })();",
+                "  return a;",
+                "}",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn extern_functions_are_not_emitted() {
+        assert_eq!(
+            gen_js("extern fn now(): int\nlet t = now()"),
+            "export const t = now();\n"
+        )
+    }
+
+    #[test]
+    fn nested_functions() {
+        assert_eq!(
+            gen_js("fn outer(): int {\n    fn inner(x: int): int = x * 2\n    inner(21)\n}"),
+            [
+                "export function outer() {",
+                "  function inner(x) {",
+                "    return x * 2;",
+                "  }",
+                "  return inner(21);",
+                "}",
+                "",
+            ]
+            .join("\n")
+        )
+    }
 }
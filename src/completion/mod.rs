@@ -0,0 +1,244 @@
+//! Completion candidates for the name being typed at a cursor.
+//!
+//! The source doesn't have to be valid: the module is parsed with error
+//! recovery, so the line being edited doesn't hide the rest of it.
+
+use crate::{
+    analyzer::{
+        imports::ImportedExports,
+        symbols::{SymbolKind, SymbolTable},
+    },
+    parser::{
+        ast::{Module, Stmt},
+        Parser, KEYWORDS,
+    },
+};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CompletionKind {
+    Symbol(SymbolKind),
+    Keyword,
+    /// Name exported by the module of a namespace import
+    Export,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+    /// Signature of functions and the type of parameters
+    pub detail: Option<String>,
+}
+
+/// Candidates for the name which ends at `offset`, best first. Each of
+/// them replaces the part of the name before the cursor.
+///
+/// Names in scope come before keywords and closer scopes before outer
+/// ones. Names starting with the typed text in the same case come
+/// before names which only match ignoring case. After `ns.`, where `ns`
+/// is a namespace import, the exports of its module in `imported` are
+/// the candidates. Other members aren't known without types.
+///
+/// An offset past the end of the source or inside of a character is
+/// moved back to the closest character boundary.
+pub fn complete(source: &str, offset: usize, imported: &ImportedExports) -> Vec<Completion> {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &source[..offset];
+    let prefix_len = before.len()
+        - before
+            .trim_end_matches(|c: char| c.is_ascii_alphanumeric())
+            .len();
+    let start = offset - prefix_len;
+    let prefix = &before[start..];
+
+    // A name which is being declared has nothing to complete
+    if declares_name(&before[..start]) {
+        return Vec::new();
+    }
+
+    let (module, _) = Parser::new().parse_recovering(source);
+    let table = SymbolTable::build(&module);
+    if let Some(receiver) = before[..start].strip_suffix('.') {
+        let exports = namespace_exports(&module, &table, receiver, imported);
+        let members = exports.iter().map(|name| Completion {
+            label: name.clone(),
+            kind: CompletionKind::Export,
+            detail: None,
+        });
+        return rank(members.collect(), prefix);
+    }
+
+    let symbols = table.symbols_in_scope(start).into_iter().map(|id| {
+        let symbol = table.symbol(id);
+        Completion {
+            label: symbol.name.name.clone(),
+            kind: CompletionKind::Symbol(symbol.kind),
+            detail: symbol.detail.clone(),
+        }
+    });
+    let keywords = KEYWORDS.iter().map(|k| Completion {
+        label: k.to_string(),
+        kind: CompletionKind::Keyword,
+        detail: None,
+    });
+    rank(symbols.chain(keywords).collect(), prefix)
+}
+
+// Candidates starting with the prefix, the ones in the same case first
+fn rank(candidates: Vec<Completion>, prefix: &str) -> Vec<Completion> {
+    let lowercase = prefix.to_lowercase();
+    let (mut exact, ignoring_case): (Vec<_>, Vec<_>) = candidates
+        .into_iter()
+        .filter(|c| c.label.to_lowercase().starts_with(&lowercase))
+        .partition(|c| c.label.starts_with(prefix));
+    exact.extend(ignoring_case);
+    exact
+}
+
+// Exports of the module when the text ends with the name of a namespace
+// import, nothing for any other receiver
+fn namespace_exports<'a>(
+    module: &Module,
+    table: &SymbolTable,
+    before: &str,
+    imported: &'a ImportedExports,
+) -> &'a [String] {
+    let name_len = before.len()
+        - before
+            .trim_end_matches(|c: char| c.is_ascii_alphanumeric())
+            .len();
+    let name_start = before.len() - name_len;
+    if name_len == 0 || before[..name_start].ends_with('.') {
+        return &[];
+    }
+    let name = &before[name_start..];
+    let namespace = table
+        .symbols_in_scope(name_start)
+        .into_iter()
+        .map(|id| table.symbol(id))
+        .find(|symbol| symbol.name.name == name)
+        .filter(|symbol| symbol.kind == SymbolKind::Import);
+    let Some(namespace) = namespace else {
+        return &[];
+    };
+    module
+        .statements
+        .iter()
+        .find_map(|stmt| match stmt {
+            Stmt::Import {
+                namespace: Some(ns),
+                path,
+                ..
+            } if ns.span == namespace.name.span => imported.get(path),
+            _ => None,
+        })
+        .map_or(&[], Vec::as_slice)
+}
+
+// Whether the text ends with a keyword after which a new name is written
+fn declares_name(before: &str) -> bool {
+    let before = before.trim_end();
+    ["let", "mut", "const", "fn"].iter().any(|k| {
+        before
+            .strip_suffix(k)
+            .is_some_and(|rest| !rest.ends_with(|c: char| c.is_ascii_alphanumeric()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyzer::{imports::ImportedExports, symbols::SymbolKind};
+
+    use super::{complete, CompletionKind};
+
+    fn labels(source: &str) -> Vec<String> {
+        let offset = source.find('|').expect("the source should have a cursor");
+        let source = source.replace('|', "");
+        let imported = ImportedExports::from([(
+            "math.sky".to_string(),
+            vec!["sin".to_string(), "sqrt".to_string(), "PI".to_string()],
+        )]);
+        complete(&source, offset, &imported)
+            .into_iter()
+            .map(|c| c.label)
+            .collect()
+    }
+
+    #[test]
+    fn names_in_scope() {
+        let source = "fn helper(x: int): int = x\nfn main() {\n    let value = 1\n    let Vector = 2\n    v|\n}";
+        assert_eq!(labels(source), vec!["value", "Vector"]);
+        assert_eq!(
+            labels("fn f(a: int) {\n    let b = 1\n    |\n}\nfn g() {}")
+                .into_iter()
+                .take(4)
+                .collect::<Vec<_>>(),
+            vec!["b", "a", "g", "f"]
+        );
+    }
+
+    #[test]
+    fn functions_have_signatures() {
+        let source = "async fn load(url: string): string = url\nlo";
+        let completions = complete(source, source.len(), &ImportedExports::new());
+        assert_eq!(completions.len(), 1);
+        assert_eq!(
+            completions[0].kind,
+            CompletionKind::Symbol(SymbolKind::Function)
+        );
+        assert_eq!(
+            completions[0].detail.as_deref(),
+            Some("async fn load(url: string): string")
+        );
+    }
+
+    #[test]
+    fn keywords() {
+        assert_eq!(labels("let a = 1\nt|"), vec!["try", "throw"]);
+        assert_eq!(labels("let iffy = 1\nif|"), vec!["iffy", "if"]);
+    }
+
+    #[test]
+    fn broken_lines_are_skipped() {
+        assert_eq!(
+            labels("let first = 1\nlet = )\nlet second = 2\nfn f() {\n    f|\n}"),
+            vec!["first", "f", "fn", "finally"]
+        );
+    }
+
+    #[test]
+    fn offsets_are_clamped() {
+        let source = "let value = 1\nva";
+        assert_eq!(complete(source, 100, &ImportedExports::new()).len(), 1);
+        // In the middle of `é`, the cursor moves back to right after `v`
+        let source = "let value = \"é\"\nvé";
+        let completions = complete(source, source.len() - 1, &ImportedExports::new());
+        assert_eq!(completions[0].label, "value");
+    }
+
+    #[test]
+    fn namespace_members() {
+        assert_eq!(
+            labels("import * as math from \"math.sky\"\nmath.s|"),
+            vec!["sin", "sqrt"]
+        );
+        assert_eq!(labels("import math as m\nm.|"), vec!["sin", "sqrt", "PI"]);
+        assert_eq!(
+            labels("import math as m\nfn f() {\n    m.p|\n}"),
+            vec!["PI"]
+        );
+        // Only namespace imports have known members
+        assert!(labels("import math::{sin}\nsin.|").is_empty());
+        assert!(labels("import math as m\nlet x = 1\nx.m.|").is_empty());
+    }
+
+    #[test]
+    fn nothing_to_complete() {
+        assert!(labels("let value = 1\nvalue.v|").is_empty());
+        assert!(labels("let value = 1\nlet v|").is_empty());
+        assert!(labels("let value = 1\nfn v|").is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+
+use peg::str::LineCol;
+
+use crate::parser::ast::Span;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Problem found in the source
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    /// Names which may have been meant instead of the one at the span,
+    /// closest first. Each of them can replace the span as a fix.
+    pub suggestions: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: String, span: Span) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+            span,
+            suggestions: Vec::new(),
+        }
+    }
+
+    pub fn warning(message: String, span: Span) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message,
+            span,
+            suggestions: Vec::new(),
+        }
+    }
+
+    pub fn with_suggestions(mut self, suggestions: Vec<String>) -> Self {
+        self.suggestions = suggestions;
+        self
+    }
+
+    /// Formats the diagnostic the same way as parse errors,
+    /// e.g. `warning at 2:5: unused variable `a``, followed by
+    /// the suggestions, e.g. `, did you mean `b` or `c`?`
+    pub fn render(&self, source: &str) -> String {
+        let pos = line_col(source, self.span.start);
+        let mut rendered = format!("{} at {}: {}", self.severity, pos, self.message);
+        if let Some((last, others)) = self.suggestions.split_last() {
+            let quoted = |s: &String| format!("`{}`", s);
+            rendered.push_str(", did you mean ");
+            if !others.is_empty() {
+                let others = others.iter().map(quoted).collect::<Vec<_>>();
+                rendered.push_str(&others.join(", "));
+                rendered.push_str(" or ");
+            }
+            rendered.push_str(&quoted(last));
+            rendered.push('?');
+        }
+        rendered
+    }
+}
+
+/// Position of the byte `offset` in `source`, both line and column start at 1
+pub fn line_col(source: &str, offset: usize) -> LineCol {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = match before.rfind('\n') {
+        Some(i) => before[i + 1..].chars().count() + 1,
+        None => before.chars().count() + 1,
+    };
+    LineCol {
+        line,
+        column,
+        offset,
+    }
+}
@@ -0,0 +1,11 @@
+pub mod analyzer;
+pub mod compiler;
+pub mod completion;
+pub mod error;
+pub mod parser;
+pub mod rewrite;
+pub mod project;
+// Shared with the integration tests
+#[cfg(test)]
+#[path = "../tests/common/mod.rs"]
+mod snapshot;
@@ -1,34 +1,162 @@
-use compiler::gen;
+use sky::analyzer::analyze;
+use sky::analyzer::consts::ImportedConsts;
+use sky::analyzer::imports::ImportedExports;
+use sky::analyzer::numeric::truncate_integer_division;
+use sky::compiler::gen;
+use sky::error::Severity;
+use sky::parser::{
+    ast::{Module, Stmt},
+    LanguageVersion, Parser,
+};
+use sky::project::{build, imported_consts, imported_exports, single_file_import, Package};
 
-use crate::parser::parse;
+use std::io::prelude::*;
+use std::path::Path;
+use std::process::{Command, ExitCode};
+use std::{
+    env::{args, current_dir},
+    error::Error,
+    fs::File,
+};
 
-mod error;
-mod parser;
-mod analyzer;
-mod compiler;
+static USAGE: &str = "Usage:
+  sky [build] [--target=js] [--emit=js|ast] [--language-version=1|2] path/to/file.sk
+  sky build [--release | --profile=<name>]
+  sky run [--release | --profile=<name>]";
 
-use std::io::prelude::*;
-use std::{env::args, error::Error, fs::File};
+fn main() -> Result<ExitCode, Box<dyn Error>> {
+    let mut args = args().skip(1).peekable();
+
+    // `sky path/to/file.sk` is a shorthand for `sky build path/to/file.sk`
+    let mut run = false;
+    match args.peek().map(String::as_str) {
+        Some("build") => {
+            args.next();
+        }
+        Some("run") => {
+            args.next();
+            run = true;
+        }
+        _ => {}
+    }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = args().collect();
-    let path: Option<String> = args.get(1).cloned();
+    let mut target = "js".to_string();
+    let mut emit = "js".to_string();
+    let mut profile = "dev".to_string();
+    let mut version = LanguageVersion::LATEST;
+    let mut path: Option<String> = None;
+    for arg in args {
+        if let Some(t) = arg.strip_prefix("--target=") {
+            target = t.to_string();
+        } else if let Some(e) = arg.strip_prefix("--emit=") {
+            emit = e.to_string();
+        } else if let Some(v) = arg.strip_prefix("--language-version=") {
+            let Some(v) = LanguageVersion::from_name(v) else {
+                println!("Unknown language version `{}`, supported versions: 1, 2", v);
+                return Ok(ExitCode::FAILURE);
+            };
+            version = v;
+        } else if let Some(p) = arg.strip_prefix("--profile=") {
+            profile = p.to_string();
+        } else if arg == "--release" {
+            profile = "release".to_string();
+        } else if path.is_none() && !run && !arg.starts_with("--") {
+            path = Some(arg);
+        } else {
+            println!("Unexpected argument `{}`\n{}", arg, USAGE);
+            return Ok(ExitCode::FAILURE);
+        }
+    }
+
+    if target != "js" {
+        println!("Unknown target `{}`, supported targets: js", target);
+        return Ok(ExitCode::FAILURE);
+    }
+    if emit != "js" && emit != "ast" {
+        println!("Unknown emit kind `{}`, supported kinds: js, ast", emit);
+        return Ok(ExitCode::FAILURE);
+    }
 
     if let Some(p) = path {
-        let mut file = File::open(p)?;
+        let mut file = File::open(&p)?;
         let mut source = String::new();
         file.read_to_string(&mut source)?;
-        let ast = parse(&source);
-        match ast {
-            Ok(ast) => {
-                println!("{}", gen(ast));
-            }
-            Err(err) => {
-                println!("{}", err);
+        let (mut ast, syntax_errors) = Parser::with_version(version).parse_recovering(&source);
+        for diagnostic in &syntax_errors {
+            eprintln!("{}", diagnostic.render(&source));
+        }
+        if syntax_errors.iter().any(|d| d.severity == Severity::Error) {
+            return Ok(ExitCode::FAILURE);
+        }
+        let resolve = |from: &Path, import: &str| Some((from.parent()?.join(import), version));
+        let imported = imported_consts(Path::new(&p), &ast, &resolve, &mut Vec::new());
+        let exports = imported_exports(Path::new(&p), &ast, &resolve);
+        if !report(&source, &ast, &imported, &exports) {
+            return Ok(ExitCode::FAILURE);
+        }
+        if emit == "ast" {
+            print!("{}", ast.dump());
+        } else {
+            truncate_integer_division(&mut ast, &imported);
+            // Imported modules are expected to be compiled next to their sources
+            let from = current_dir()?.join(&p);
+            for stmt in &mut ast.statements {
+                if let Stmt::Import { path: import, .. } = stmt {
+                    *import = single_file_import(&from, import);
+                }
             }
+            println!("{}", gen(ast));
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // Without a file, the package containing the current directory is built
+    let package = match Package::current() {
+        Ok(package) => package,
+        Err(err) => {
+            println!("{}\n{}", err, USAGE);
+            return Ok(ExitCode::FAILURE);
         }
-    } else {
-        println!("No provided path/to/file.sk")
+    };
+    let result = match build(&package, &profile) {
+        Ok(result) => result,
+        Err(err) => {
+            println!("{}", err);
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+    for message in &result.messages {
+        eprintln!("{}", message);
+    }
+    if !result.success {
+        println!("Could not build `{}`", package.manifest.name);
+        return Ok(ExitCode::FAILURE);
+    }
+    if run {
+        // The exit status of the program is passed through,
+        // a program killed by a signal has none
+        let status = Command::new("node").arg(&result.entry).status()?;
+        return Ok(match status.code() {
+            Some(code) => ExitCode::from(code as u8),
+            None => ExitCode::FAILURE,
+        });
+    }
+    println!("Built `{}` ({} profile)", package.manifest.name, profile);
+    Ok(ExitCode::SUCCESS)
+}
+
+// Diagnostics go to stderr, so they never end up in the emitted code.
+// Returns false when there were errors.
+fn report(
+    source: &str,
+    ast: &Module,
+    imported: &ImportedConsts,
+    exports: &ImportedExports,
+) -> bool {
+    let mut ok = true;
+    for diagnostic in analyze(ast, imported, exports) {
+        ok &= diagnostic.severity != Severity::Error;
+        eprintln!("{}", diagnostic.render(source));
     }
-    Ok(())
+    ok
 }
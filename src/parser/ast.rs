@@ -1,48 +1,125 @@
-#[derive(Debug, PartialEq)]
+use self::pattern::Pattern;
+
+/// Byte range of a node in the source
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset <= self.end
+    }
+}
+
+/// Name of a binding or a reference to one
+#[derive(Debug, PartialEq, Clone)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+impl Ident {
+    pub fn new(name: &str, span: Span) -> Self {
+        Self {
+            name: name.to_string(),
+            span,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Default)]
 pub struct Module {
     pub statements: Vec<Stmt>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Stmt {
+    /// `import { a, b as c } from "path"` or `import * as ns from "path"`,
+    /// `import mod::{a, b as c}` and `import mod as ns` for the module
+    /// `mod.sky`. `pub import` also exports the imported names
     Import {
         symbols: Vec<ImportedSymbol>,
+        /// Name the whole module is bound to by `import * as`
+        namespace: Option<Ident>,
         path: String,
+        is_pub: bool,
     },
+    /// `let`, which binds every name of the pattern
     Var {
-        name: String,
+        pattern: Pattern,
         is_mut: bool,
         value: Expr,
     },
     Const {
-        name: String,
+        name: Ident,
         value: Expr,
     },
     Function {
-        name: String,
+        name: Ident,
+        is_async: bool,
+        params: Vec<FunctionParam>,
+        ret_type: TypeUsage,
+        body: Block,
+    },
+    /// Function implemented by the host, declared with `extern fn`
+    ExternFunction {
+        name: Ident,
+        is_async: bool,
         params: Vec<FunctionParam>,
         ret_type: TypeUsage,
-        body: Vec<Stmt>,
+    },
+    Try {
+        body: Block,
+        catch: Option<CatchClause>,
+        finally: Option<Block>,
+    },
+    Throw {
+        value: Expr,
+        span: Span,
     },
     Expr(Expr),
 }
 
-#[derive(Debug, PartialEq)]
+/// Statements of a function, `if`, `try`, `catch` or `finally` body
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Block {
+    pub statements: Vec<Stmt>,
+    /// From the opening to the closing brace, the expression
+    /// for functions defined with `=`
+    pub span: Span,
+}
+
+impl Block {
+    pub fn new(statements: Vec<Stmt>, span: Span) -> Self {
+        Self { statements, span }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CatchClause {
+    pub binding: Option<Ident>,
+    pub body: Block,
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct FunctionParam {
-    pub name: String,
+    pub name: Ident,
     pub r#type: TypeUsage,
 }
 
 impl FunctionParam {
-    pub fn new(name: &str, t: TypeUsage) -> Self {
-        Self {
-            name: name.to_string(),
-            r#type: t,
-        }
+    pub fn new(name: Ident, t: TypeUsage) -> Self {
+        Self { name, r#type: t }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct TypeUsage {
     pub name: String,
     pub params: Vec<TypeUsage>,
@@ -57,10 +134,12 @@ impl TypeUsage {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct ImportedSymbol {
     pub name: String,
     pub imported_as: Option<String>,
+    /// Span of the name the symbol is bound to in the importing module
+    pub span: Span,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -87,6 +166,14 @@ impl BinaryOpKind {
             BinaryOpKind::Rem => "%",
         }
     }
+
+    /// Binding power of the operator, higher binds tighter
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOpKind::Add | BinaryOpKind::Sub => 1,
+            BinaryOpKind::Mul | BinaryOpKind::Div | BinaryOpKind::Rem => 2,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -94,7 +181,7 @@ pub enum Expr {
     Integer(i32),
     Float(f32),
     String(String),
-    Ident(String),
+    Ident(Ident),
     BinaryOp {
         kind: BinaryOpKind,
         left: Box<Expr>,
@@ -106,12 +193,31 @@ pub enum Expr {
     },
     DotAccess {
         target: Box<Expr>,
-        name: String,
+        name: Ident,
     },
     BracketAccess {
         target: Box<Expr>,
         expr: Box<Expr>
-    }
+    },
+    Await(Box<Expr>),
+    /// Explicit conversion `expr as Type`
+    Cast {
+        expr: Box<Expr>,
+        r#type: TypeUsage,
+    },
+    /// `if` together with all of its `else if` branches, in source order
+    If {
+        branches: Vec<IfBranch>,
+        else_body: Option<Block>,
+    },
+    /// Missing or unparsable part of the source
+    Error(Span),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct IfBranch {
+    pub condition: Expr,
+    pub body: Block,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -164,11 +270,16 @@ impl Expr {
 }
 
 pub mod pattern {
-    #[derive(Debug, PartialEq)]
+    use super::Ident;
+
+    #[derive(Debug, PartialEq, Clone)]
     pub enum Pattern {
-        Tuple(Vec<Box<Pattern>>),
+        /// Name the matched value is bound to
+        Binding(Ident),
+        Tuple(Vec<Pattern>),
         Struct {
-            name: String,
+            /// `None` for `{ x, y }`, which matches any value with the fields
+            name: Option<String>,
             fields: Vec<StructField>,
         },
         Integer(i32),
@@ -176,7 +287,21 @@ pub mod pattern {
         String(String),
     }
 
-    #[derive(Debug, PartialEq)]
+    impl Pattern {
+        /// Every name bound by the pattern, in source order
+        pub fn bindings(&self) -> Vec<&Ident> {
+            match self {
+                Pattern::Binding(name) => vec![name],
+                Pattern::Tuple(items) => items.iter().flat_map(Pattern::bindings).collect(),
+                Pattern::Struct { fields, .. } => {
+                    fields.iter().flat_map(|f| f.pattern.bindings()).collect()
+                }
+                Pattern::Integer(_) | Pattern::Float(_) | Pattern::String(_) => Vec::new(),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq, Clone)]
     pub struct StructField {
         pub name: String,
         pub pattern: Pattern,
@@ -0,0 +1,418 @@
+//! Compact and stable textual representation of the AST.
+//!
+//! Every node is printed as an s-expression. Statements are put on
+//! their own lines and nested bodies are indented, so a changed parse
+//! shows up as a small line diff.
+
+use super::ast::{
+    pattern::Pattern, CallArgument, CatchClause, Expr, FunctionParam, IfBranch, ImportedSymbol,
+    Module, Stmt, TypeUsage,
+};
+
+static SPACE: &str = "  ";
+
+impl Module {
+    pub fn dump(&self) -> String {
+        let mut buf = String::from("(module");
+        dump_body(&mut buf, 1, &self.statements);
+        buf.push_str(")\n");
+        buf
+    }
+}
+
+impl Stmt {
+    pub fn dump(&self) -> String {
+        let mut buf = String::new();
+        dump_stmt(&mut buf, 0, self);
+        buf
+    }
+}
+
+impl Expr {
+    pub fn dump(&self) -> String {
+        let mut buf = String::new();
+        dump_expr(&mut buf, 0, self);
+        buf
+    }
+}
+
+impl TypeUsage {
+    pub fn dump(&self) -> String {
+        let mut buf = self.name.clone();
+        if !self.params.is_empty() {
+            let params = self.params.iter().map(TypeUsage::dump).collect::<Vec<_>>();
+            buf.push('<');
+            buf.push_str(params.join(", ").as_str());
+            buf.push('>');
+        }
+        buf
+    }
+}
+
+// Puts every statement on a new line, `deep` levels deep
+fn dump_body(buf: &mut String, deep: usize, body: &[Stmt]) {
+    for stmt in body {
+        buf.push('\n');
+        buf.push_str(SPACE.repeat(deep).as_str());
+        dump_stmt(buf, deep, stmt);
+    }
+}
+
+fn dump_stmt(buf: &mut String, deep: usize, stmt: &Stmt) {
+    match stmt {
+        Stmt::Import {
+            symbols,
+            namespace,
+            path,
+            is_pub,
+        } => {
+            buf.push_str(if *is_pub { "(pub import " } else { "(import " });
+            dump_string(buf, path);
+            if let Some(namespace) = namespace {
+                buf.push_str(" (* ");
+                buf.push_str(&namespace.name);
+                buf.push(')');
+            }
+            for sym in symbols {
+                buf.push(' ');
+                dump_imported_symbol(buf, sym);
+            }
+            buf.push(')');
+        }
+        Stmt::Var {
+            pattern,
+            is_mut,
+            value,
+        } => {
+            buf.push_str(if *is_mut { "(let mut " } else { "(let " });
+            dump_pattern(buf, pattern);
+            buf.push(' ');
+            dump_expr(buf, deep, value);
+            buf.push(')');
+        }
+        Stmt::Const { name, value } => {
+            buf.push_str("(const ");
+            buf.push_str(&name.name);
+            buf.push(' ');
+            dump_expr(buf, deep, value);
+            buf.push(')');
+        }
+        Stmt::Function {
+            name,
+            is_async,
+            params,
+            ret_type,
+            body,
+        } => {
+            buf.push('(');
+            if *is_async {
+                buf.push_str("async ");
+            }
+            dump_signature(buf, "fn", &name.name, params, ret_type);
+            dump_body(buf, deep + 1, &body.statements);
+            buf.push(')');
+        }
+        Stmt::ExternFunction {
+            name,
+            is_async,
+            params,
+            ret_type,
+        } => {
+            buf.push_str("(extern ");
+            if *is_async {
+                buf.push_str("async ");
+            }
+            dump_signature(buf, "fn", &name.name, params, ret_type);
+            buf.push(')');
+        }
+        Stmt::Try {
+            body,
+            catch,
+            finally,
+        } => {
+            buf.push_str("(try");
+            dump_body(buf, deep + 1, &body.statements);
+            if let Some(CatchClause { binding, body }) = catch {
+                buf.push('\n');
+                buf.push_str(SPACE.repeat(deep + 1).as_str());
+                buf.push_str("(catch");
+                if let Some(binding) = binding {
+                    buf.push(' ');
+                    buf.push_str(&binding.name);
+                }
+                dump_body(buf, deep + 2, &body.statements);
+                buf.push(')');
+            }
+            if let Some(body) = finally {
+                buf.push('\n');
+                buf.push_str(SPACE.repeat(deep + 1).as_str());
+                buf.push_str("(finally");
+                dump_body(buf, deep + 2, &body.statements);
+                buf.push(')');
+            }
+            buf.push(')');
+        }
+        Stmt::Throw { value, .. } => {
+            buf.push_str("(throw ");
+            dump_expr(buf, deep, value);
+            buf.push(')');
+        }
+        Stmt::Expr(expr) => dump_expr(buf, deep, expr),
+    }
+}
+
+fn dump_imported_symbol(buf: &mut String, sym: &ImportedSymbol) {
+    match &sym.imported_as {
+        Some(alias) => {
+            buf.push_str("(as ");
+            buf.push_str(&sym.name);
+            buf.push(' ');
+            buf.push_str(alias);
+            buf.push(')');
+        }
+        None => buf.push_str(&sym.name),
+    }
+}
+
+fn dump_signature(
+    buf: &mut String,
+    kw: &str,
+    name: &str,
+    params: &[FunctionParam],
+    ret_type: &TypeUsage,
+) {
+    buf.push_str(kw);
+    buf.push(' ');
+    buf.push_str(name);
+    buf.push_str(" (");
+    let p = params
+        .iter()
+        .map(|p| format!("({} {})", p.name.name, p.r#type.dump()))
+        .collect::<Vec<_>>();
+    buf.push_str(p.join(" ").as_str());
+    buf.push_str(") ");
+    buf.push_str(ret_type.dump().as_str());
+}
+
+fn dump_expr(buf: &mut String, deep: usize, expr: &Expr) {
+    match expr {
+        Expr::Integer(i) => buf.push_str(i.to_string().as_str()),
+        // Debug keeps the fraction part, so `1.0` never looks like `1`
+        Expr::Float(f) => buf.push_str(format!("{:?}", f).as_str()),
+        Expr::String(s) => dump_string(buf, s),
+        Expr::Ident(i) => buf.push_str(&i.name),
+        Expr::BinaryOp { kind, left, right } => {
+            buf.push('(');
+            buf.push_str(kind.to_op());
+            buf.push(' ');
+            dump_expr(buf, deep, left);
+            buf.push(' ');
+            dump_expr(buf, deep, right);
+            buf.push(')');
+        }
+        Expr::Call { target, arguments } => {
+            buf.push_str("(call ");
+            dump_expr(buf, deep, target);
+            for arg in arguments {
+                buf.push(' ');
+                dump_call_argument(buf, deep, arg);
+            }
+            buf.push(')');
+        }
+        Expr::DotAccess { target, name } => {
+            buf.push_str("(. ");
+            dump_expr(buf, deep, target);
+            buf.push(' ');
+            buf.push_str(&name.name);
+            buf.push(')');
+        }
+        Expr::BracketAccess { target, expr } => {
+            buf.push_str("([] ");
+            dump_expr(buf, deep, target);
+            buf.push(' ');
+            dump_expr(buf, deep, expr);
+            buf.push(')');
+        }
+        Expr::Await(expr) => {
+            buf.push_str("(await ");
+            dump_expr(buf, deep, expr);
+            buf.push(')');
+        }
+        Expr::Cast { expr, r#type } => {
+            buf.push_str("(as ");
+            dump_expr(buf, deep, expr);
+            buf.push(' ');
+            buf.push_str(r#type.dump().as_str());
+            buf.push(')');
+        }
+        Expr::If {
+            branches,
+            else_body,
+        } => {
+            buf.push_str("(cond");
+            for IfBranch { condition, body } in branches {
+                buf.push('\n');
+                buf.push_str(SPACE.repeat(deep + 1).as_str());
+                buf.push('(');
+                dump_expr(buf, deep + 1, condition);
+                dump_body(buf, deep + 2, &body.statements);
+                buf.push(')');
+            }
+            if let Some(body) = else_body {
+                buf.push('\n');
+                buf.push_str(SPACE.repeat(deep + 1).as_str());
+                buf.push_str("(else");
+                dump_body(buf, deep + 2, &body.statements);
+                buf.push(')');
+            }
+            buf.push(')');
+        }
+        Expr::Error(_) => buf.push_str("(error)"),
+    }
+}
+
+fn dump_call_argument(buf: &mut String, deep: usize, arg: &CallArgument) {
+    match &arg.name {
+        Some(name) => {
+            buf.push_str("(= ");
+            buf.push_str(name);
+            buf.push(' ');
+            dump_expr(buf, deep, &arg.expr);
+            buf.push(')');
+        }
+        None => dump_expr(buf, deep, &arg.expr),
+    }
+}
+
+// Bindings are written as their name, fields binding
+// their own name as just the name
+fn dump_pattern(buf: &mut String, pattern: &Pattern) {
+    match pattern {
+        Pattern::Binding(name) => buf.push_str(&name.name),
+        Pattern::Tuple(items) => {
+            buf.push_str("(tuple");
+            for item in items {
+                buf.push(' ');
+                dump_pattern(buf, item);
+            }
+            buf.push(')');
+        }
+        Pattern::Struct { name, fields } => {
+            buf.push_str("(struct");
+            if let Some(name) = name {
+                buf.push(' ');
+                buf.push_str(name);
+            }
+            for field in fields {
+                buf.push(' ');
+                match &field.pattern {
+                    Pattern::Binding(b) if b.name == field.name => buf.push_str(&b.name),
+                    pattern => {
+                        buf.push('(');
+                        buf.push_str(&field.name);
+                        buf.push(' ');
+                        dump_pattern(buf, pattern);
+                        buf.push(')');
+                    }
+                }
+            }
+            buf.push(')');
+        }
+        Pattern::Integer(i) => buf.push_str(i.to_string().as_str()),
+        Pattern::Float(f) => buf.push_str(format!("{:?}", f).as_str()),
+        Pattern::String(s) => dump_string(buf, s),
+    }
+}
+
+// String literals keep their escape sequences as written in the source
+fn dump_string(buf: &mut String, s: &str) {
+    buf.push('"');
+    buf.push_str(s);
+    buf.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        parser::{parse, parser, Parser},
+        snapshot,
+    };
+
+    /// Compares the AST dump of `source` with `snapshots/<name>.snap`
+    fn assert_snapshot(name: &str, source: &str) {
+        let actual = match parse(source) {
+            Ok(module) => module.dump(),
+            Err(err) => format!("error: {}\n", err),
+        };
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/parser/snapshots")
+            .join(format!("{}.snap", name));
+        if let Err(err) = snapshot::compare(&path, &actual) {
+            panic!("{}", err);
+        }
+    }
+
+    #[test]
+    fn expr_dump() {
+        assert_eq!(
+            parser::expr("await f(a.b[0], n = 1.0) * 2 + \"s\"", &Parser::new())
+                .unwrap()
+                .dump(),
+            "(+ (* (await (call f ([] (. a b) 0) (= n 1.0))) 2) \"s\")"
+        );
+    }
+
+    #[test]
+    fn snapshot_definitions() {
+        assert_snapshot(
+            "definitions",
+            r#"import { a as b, c } from "./mod.sk"
+let x = 1
+let mut y = x + 2.5
+const z = "str"
+extern async fn fetch(url: string): Response
+fn add(a: int, b: Option<int>): int {
+    a + b
+}
+fn noop() {}"#,
+        );
+    }
+
+    #[test]
+    fn snapshot_if_chains() {
+        assert_snapshot(
+            "if_chains",
+            r#"fn sign(x: int): int {
+    if x {
+        if positive(x) { 1 } else { 0 - 1 }
+    } else if other {
+        log(x)
+        2
+    } else {
+        0
+    }
+}
+let y = if a { 1 } else { 2 } * 3
+if a { f() }
+(g)()"#,
+        );
+    }
+
+    #[test]
+    fn snapshot_exceptions() {
+        assert_snapshot(
+            "exceptions",
+            r#"async fn main() {
+    try {
+        await run()
+    } catch (e) {
+        throw e
+    } finally {
+        close()
+    }
+}"#,
+        );
+    }
+}
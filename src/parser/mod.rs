@@ -1,18 +1,27 @@
 use peg::{error::ParseError, str::LineCol};
 
-use self::ast::Module;
+use std::fmt;
+
+use self::ast::{Expr, Module, Span, Stmt};
+use crate::error::Diagnostic;
 
 pub mod ast;
+mod dump;
 mod stmt;
 
 peg::parser! {
-    grammar parser() for str {
+    grammar parser(config: &Parser) for str {
 
     use ast::{
+        Block,
+        CatchClause,
         Expr,
         FunctionParam,
+        Ident,
+        IfBranch,
         ImportedSymbol,
         Module,
+        Span,
         Stmt,
         TypeUsage,
         CallArgument
@@ -32,6 +41,20 @@ peg::parser! {
     rule escape_sequence() = "\\\\" / "\\\"" / "\\\'" / "\\n" / "\\r" / "\\t" / "\\0"
 
     rule alphanumeric() = (alpha() / numeric())
+
+    // Succeeds when the whitespace right before the position has a line break
+    rule line_break_before() = #{|input, pos| {
+        let before = &input[..pos];
+        if before[before.trim_end().len()..].contains('\n') {
+            ::peg::RuleResult::Matched(pos, ())
+        } else {
+            ::peg::RuleResult::Failed
+        }
+    }}
+    // Line breaks only end statements from language version 2
+    rule line_starts_statement() = quiet!{
+        line_break_before() {? if config.version >= LanguageVersion::V2 { Ok(()) } else { Err("") } }
+    }
     rule literal_char() = escape_sequence() / (!"\"" any())
 
 
@@ -46,18 +69,40 @@ peg::parser! {
     rule spaced<T>(x: rule<T>) -> T =
         sp() r:x() sp() { r }
 
-    rule curly_braced<T>(r: rule<T>) -> T = spaced(<"{">) r:r() spaced(<"}">) { r }
-    rule angle_braced<T>(r: rule<T>) -> T = spaced(<"<">) r:r() spaced(<">">) { r }
-    rule round_braced<T>(r: rule<T>) -> T = spaced(<"(">) r:r() spaced(<")">) { r }
-    rule rect_braced<T>(r: rule<T>) -> T = spaced(<"[">) r:r() spaced(<"]">) { r }
+    rule curly_braced<T>(r: rule<T>) -> T = spaced(<"{">) r:r() closing(<"}">) { r }
+    rule angle_braced<T>(r: rule<T>) -> T = spaced(<"<">) r:r() closing(<">">) { r }
+    rule round_braced<T>(r: rule<T>) -> T = spaced(<"(">) r:r() closing(<")">) { r }
+    rule rect_braced<T>(r: rule<T>) -> T = spaced(<"[">) r:r() closing(<"]">) { r }
+
+    // Partial input may end before a bracket is closed
+    rule closing(c: rule<()>) = spaced(<c()>) / sp() partial_eof()
 
-    rule import_kw() = spaced(<"import">)
+    // End of the input, only accepted when parsing partial input
+    // and when recovering from syntax errors
+    rule partial_eof() = quiet!{
+        ![_] {? if config.partial || config.recover { Ok(()) } else { Err("") } }
+    }
+
+    // Only matches when recovering from syntax errors
+    rule recovering() = quiet!{ position!() {? if config.recover { Ok(()) } else { Err("") } } }
+
+    rule import_kw() = spaced(<"import" !alphanumeric()>)
     rule from_kw() = spaced(<"from">)
+    rule pub_kw() = spaced(<"pub" !alphanumeric()>)
     rule mut_kw() = spaced(<"mut">)
     rule let_kw() = spaced(<"let">)
     rule const_kw() = spaced(<"const">)
     rule fn_kw() = spaced(<"fn">)
-    rule as_kw() = spaced(<"as">)
+    rule extern_kw() = spaced(<"extern" !alphanumeric()>)
+    rule async_kw() = spaced(<"async" !alphanumeric()>)
+    rule await_kw() = spaced(<"await" !alphanumeric()>)
+    rule cast_kw() = spaced(<"as" !alphanumeric()>)
+    rule try_kw() = spaced(<"try" !alphanumeric()>)
+    rule catch_kw() = spaced(<"catch" !alphanumeric()>)
+    rule finally_kw() = spaced(<"finally" !alphanumeric()>)
+    rule throw_kw() = spaced(<"throw" !alphanumeric()>)
+    rule if_kw() = spaced(<"if" !alphanumeric()>)
+    rule else_kw() = spaced(<"else" !alphanumeric()>)
     rule assign() = spaced(<"=">)
     rule comma() = spaced(<",">)
     rule colon() = spaced(<":">)
@@ -79,6 +124,32 @@ peg::parser! {
 
     pub rule ident() -> &'input str =
         $(alpha() alphanumeric()*)
+
+    // Keywords which start a statement or a part of one can't be used as names,
+    // so a missing brace or a statement cut short isn't read as a variable
+    rule reserved() = #{|input, pos| {
+        let rest = &input[pos..];
+        let len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        if KEYWORDS.contains(&&rest[..len]) {
+            ::peg::RuleResult::Matched(pos + len, ())
+        } else {
+            ::peg::RuleResult::Failed
+        }
+    }}
+
+    // Members may be named like keywords
+    rule member_name() -> Ident =
+        sp() start:position!() n:ident() end:position!() sp() {
+            Ident::new(n, Span::new(start, end))
+        }
+
+    // Name of a binding or a reference to one
+    rule spanned_ident() -> Ident =
+        !reserved() start:position!() n:ident() end:position!() {
+            Ident::new(n, Span::new(start, end))
+        }
     //
     // </PRIMITIVES>
     //
@@ -89,17 +160,13 @@ peg::parser! {
 
     rule tuple_pattern() -> Pattern =
         "(" ps:(pattern() ** ",") ")" {
-            Pattern::Tuple(
-                ps.into_iter()
-                    .map(|p| Box::new(p))
-                    .collect()
-            )
+            Pattern::Tuple(ps)
         }
 
     rule struct_pattern() -> Pattern =
         n:struct_name()
         b:struct_body() {
-            Pattern::Struct { name: n.to_string(), fields: b }
+            Pattern::Struct { name: Some(n.to_string()), fields: b }
         }
 
         rule struct_name() -> &'input str = ident()
@@ -153,7 +220,7 @@ peg::parser! {
         params:type_param_list()? {
             TypeUsage {
                 name: name.to_string(),
-                params: params.unwrap_or_else(|| Vec::new()),
+                params: params.unwrap_or_default(),
             }
         }
 
@@ -191,8 +258,8 @@ peg::parser! {
         }
 
     rule ident_expr() -> Expr =
-        i:ident() {
-            Expr::Ident(i.to_string())
+        i:spanned_ident() {
+            Expr::Ident(i)
         }
 
     rule expr_arith() -> Expr = precedence! {
@@ -203,13 +270,36 @@ peg::parser! {
         x:(@) "/" y:@ { Expr::bin_div(x, y) }
         x:(@) "%" y:@ { Expr::bin_rem(x, y) }
         --
+        x:@ cast_kw() t:type_usage() {
+            Expr::Cast { expr: Box::new(x), r#type: t }
+        }
+        --
+        await_kw() x:@ { Expr::Await(Box::new(x)) }
+        --
+        l:@ spaced(<".">) n:(member_name() / sp() p:position!() partial_eof() { Ident::new("", Span::new(p, p)) }) {
+            Expr::DotAccess { target: Box::new(l), name: n }
+        }
+        // A `(` or `[` at the start of a line begins a new statement
+        l:@ !line_starts_statement() r:rect_braced(<expr()>) {
+            Expr::BracketAccess { target: Box::new(l), expr: Box::new(r) }
+        }
+        l:@ !line_starts_statement() args:call_arguments() {
+            Expr::Call { target: Box::new(l), arguments: args }
+        }
+        --
         e:spaced(<float()>){e}
         e:spaced(<int()>){e}
         e:spaced(<string()>){e}
+        e:if_expr() {e}
         e:spaced(<ident_expr()>){e}
         e:round_braced(<expr()>) {e}
+        e:missing_expr() {e}
     }
 
+    // Placeholder for an expression cut off by the end of partial input
+    rule missing_expr() -> Expr =
+        sp() p:position!() partial_eof() { Expr::Error(Span::new(p, p)) }
+
     rule call_arguments()-> Vec<CallArgument> =
         round_braced(<comma_separated(<call_argument()>)>)
 
@@ -224,19 +314,28 @@ peg::parser! {
         rule call_argument_name() -> &'input str =
             n:spaced(<ident()>) assign() { n }
 
-    #[cache_left_rec]
-    rule expr() -> Expr =
-        l:expr() spaced(<".">) n:ident() {
-            Expr::DotAccess { target: Box::new(l), name: n.to_string() }
-        }
-        / l:expr() r:rect_braced(<expr()>) {
-            Expr::BracketAccess { target: Box::new(l), expr: Box::new(r) }
-        }
-        / l:expr() args:call_arguments() {
-            Expr::Call { target: Box::new(l), arguments: args }
+    pub rule expr() -> Expr = expr_arith()
+
+    // Every `else` belongs to the closest `if` before it, as bodies always
+    // have braces. `else if` branches are collected into a single node.
+    pub rule if_expr() -> Expr =
+        first:if_branch()
+        others:(else_kw() b:if_branch() { b })*
+        else_body:(else_kw() b:block() { b })? {
+            let mut branches = vec![first];
+            branches.extend(others);
+            Expr::If { branches, else_body }
         }
-        / l:expr()
-        / expr_arith()
+
+        rule if_branch() -> IfBranch =
+            if_kw()
+            condition:expr()
+            body:block() {
+                IfBranch {
+                    condition,
+                    body
+                }
+            }
 
     //
     // </EXPRESSIONS>
@@ -247,15 +346,35 @@ peg::parser! {
     //
 
     pub rule import_stmt() -> Stmt =
+        is_pub:(pub_kw() { true })?
         import_kw()
-        symbols:curly_braced(<
-            imported_sumbol_list()
-        >)
-        from_kw()
-        path:string_literal() {
-            Stmt::Import { symbols, path: path.to_string() }
+        imported:(
+            symbols:curly_braced(<imported_sumbol_list()>) path:from_path() {
+                (symbols, None, path)
+            }
+            / spaced(<"*">) cast_kw() namespace:spanned_ident() path:from_path() {
+                (Vec::new(), Some(namespace), path)
+            }
+            / path:module_path() "::" symbols:curly_braced(<imported_sumbol_list()>) {
+                (symbols, None, path)
+            }
+            / path:module_path() cast_kw() namespace:spanned_ident() {
+                (Vec::new(), Some(namespace), path)
+            }
+        ) {
+            let (symbols, namespace, path) = imported;
+            Stmt::Import { symbols, namespace, path, is_pub: is_pub.is_some() }
         }
 
+        rule from_path() -> String =
+            from_kw() path:string_literal() { path.to_string() }
+
+        // `math::vector` names the module in `math/vector.sky`
+        rule module_path() -> String =
+            names:(!reserved() n:ident() { n }) ++ "::" {
+                format!("{}.sky", names.join("/"))
+            }
+
         rule imported_sumbol_list() -> Vec<ImportedSymbol> =
             symbols:spaced(<
                 comma_separated(<
@@ -264,30 +383,152 @@ peg::parser! {
             >) { symbols }
 
         rule imported_symbol() -> ImportedSymbol =
-            name:(ident())
-            imported_as:imported_symbol_alias() {
+            name:spanned_ident()
+            alias:imported_symbol_alias() {
                 ImportedSymbol {
-                    name: name.to_string(),
-                    imported_as
+                    span: alias.as_ref().unwrap_or(&name).span,
+                    name: name.name,
+                    imported_as: alias.map(|a| a.name)
                 }
             }
-        rule imported_symbol_alias() -> Option<String> =
-            alias:(as_kw() n:ident() { n })? {
-                alias.map(str::to_string)
+        rule imported_symbol_alias() -> Option<Ident> =
+            (cast_kw() n:spanned_ident() { n })?
+
+
+
+    pub rule try_stmt() -> Stmt =
+        try_kw()
+        body:block()
+        catch:catch_clause()?
+        finally:finally_clause()? {?
+            if catch.is_none() && finally.is_none() {
+                Err("catch or finally")
+            } else {
+                Ok(Stmt::Try { body, catch, finally })
+            }
+        }
+
+        rule catch_clause() -> CatchClause =
+            catch_kw()
+            binding:round_braced(<spanned_ident()>)?
+            body:block() {
+                CatchClause {
+                    binding,
+                    body
+                }
             }
 
+        rule finally_clause() -> Block =
+            finally_kw()
+            body:block() { body }
+
+    pub rule throw_stmt() -> Stmt =
+        start:position!()
+        throw_kw()
+        value:expr()
+        end:position!() {
+            Stmt::Throw {
+                value,
+                span: Span::new(start, end)
+            }
+        }
 
+    rule block() -> Block =
+        braced_block()
+        / sp() p:position!() partial_eof() { Block::new(Vec::new(), Span::new(p, p)) }
 
-    // Rule for parsing any statements
-    rule stmt() -> Stmt =
-        import_stmt()
-        / definition()
-        / e:expr() { Stmt::Expr(e) }
+        rule braced_block() -> Block =
+            sp() start:position!() "{" sp() statements:stmts() sp() end:block_end() sp() {
+                Block::new(statements, Span::new(start, end))
+            }
+
+        rule block_end() -> usize =
+            "}" p:position!() { p }
+            / p:position!() partial_eof() { p }
 
-    rule stmt_separator() =
+    // Rule for parsing any statements, the end of the input never starts one
+    rule stmt() -> Stmt =
+        !(sp() ![_])
+        s:(
+            import_stmt()
+            / try_stmt()
+            / throw_stmt()
+            / definition()
+            // An `if` at the start of a statement ends with its last block
+            / e:if_expr() { Stmt::Expr(e) }
+            / e:expr() { Stmt::Expr(e) }
+            / skipped()
+        ) { s }
+
+    // A statement ends at a line break, statements on the same
+    // line have to be separated with `;`. Anything else following
+    // a statement is skipped when recovering from errors. Before
+    // language version 2, the `;` is optional everywhere.
+    rule stmt_separator() -> Option<Stmt> =
+        semicolon() { None }
+        / sp() (line_break_before() / optional_semicolon() / expected!(STATEMENT_END)) { None }
+        / s:skipped() semicolon()? { Some(s) }
+
+    rule optional_semicolon() = #{|input, pos| {
+        if config.version < LanguageVersion::V2 {
+            ::peg::RuleResult::Matched(pos, ())
+        } else {
+            ::peg::RuleResult::Failed
+        }
+    }}
+
+    rule stmts() -> Vec<Stmt> =
+        s:(
+            first:stmt()
+            others:(sep:stmt_separator() s:stmt() { (sep, s) })* {
+                let mut stmts = vec![first];
+                for (skipped, stmt) in others {
+                    stmts.extend(skipped);
+                    stmts.push(stmt);
+                }
+                stmts
+            }
+        )?
         semicolon()?
+        skipped:skipped()?
+        rest:unparsed_rest()? {
+            let mut s = s.unwrap_or_default();
+            s.extend(skipped);
+            s.extend(rest);
+            s
+        }
+
+        // Rest of partial input that can't be parsed as a statement
+        rule unparsed_rest() -> Stmt =
+            quiet! {
+                sp() !"}" start:position!() [_]+ end:position!() ![_] {?
+                    if config.partial {
+                        Ok(Stmt::Expr(Expr::Error(Span::new(start, end))))
+                    } else {
+                        Err("")
+                    }
+                }
+            }
+
+        // Source skipped to recover from a syntax error: the rest of the line
+        // up to a `;` or an unmatched `}`, brackets inside of it may span lines
+        rule skipped() -> Stmt =
+            quiet! {
+                recovering() sp() !"}" start:position!() skipped_token()+ end:position!() {
+                    Stmt::Expr(Expr::Error(Span::new(start, end)))
+                }
+            }
+
+            rule skipped_token() =
+                skipped_group() / string_literal() / !['\n' | ';' | '}'] [_]
 
-    rule stmts() -> Vec<Stmt> = stmt() ** stmt_separator()
+            rule skipped_group() =
+                "(" skipped_nested()* ")"
+                / "[" skipped_nested()* "]"
+                / "{" skipped_nested()* "}"
+
+            rule skipped_nested() =
+                skipped_group() / string_literal() / !['(' | ')' | '[' | ']' | '{' | '}'] [_]
 
     //
     // </STATEMENTS>
@@ -298,19 +539,39 @@ peg::parser! {
     //
 
     pub rule function_definition() -> Stmt =
+        is_async:optional_async()
         fn_kw()
-        name:ident()
+        name:spanned_ident()
         params:function_param_list()
         ret_type:function_type()
         body:function_body() {
             Stmt::Function {
-                name: name.to_string(),
+                name,
+                is_async,
                 params,
                 ret_type,
                 body
             }
         }
 
+    pub rule extern_function_declaration() -> Stmt =
+        extern_kw()
+        is_async:optional_async()
+        fn_kw()
+        name:spanned_ident()
+        params:function_param_list()
+        ret_type:function_type() {
+            Stmt::ExternFunction {
+                name,
+                is_async,
+                params,
+                ret_type
+            }
+        }
+
+        rule optional_async() -> bool =
+            a:(async_kw() {})? { a.is_some() }
+
         rule function_param_list() -> Vec<FunctionParam> =
             params:round_braced(<
                 comma_separated(<
@@ -319,7 +580,7 @@ peg::parser! {
             >) { params }
 
             rule function_param() -> FunctionParam =
-                name:ident()
+                name:spanned_ident()
                 colon()
                 t:type_usage() {
                     FunctionParam::new(name, t)
@@ -333,9 +594,11 @@ peg::parser! {
                 )
             }
 
-        rule function_body() -> Vec<Stmt> =
-            sp() s:curly_braced(<stmts()>) { s }
-            / assign() s:stmt() { Vec::from([s]) }
+        rule function_body() -> Block =
+            braced_block()
+            / assign() start:position!() s:stmt() end:position!() {
+                Block::new(Vec::from([s]), Span::new(start, end))
+            }
 
     pub rule var_definition() -> Stmt =
         var()
@@ -344,22 +607,57 @@ peg::parser! {
         rule var() -> Stmt =
             let_kw()
             is_mut:optional_mut()
-            name:ident()
+            pattern:let_pattern()
             assign()
             e:expr() {
                 Stmt::Var {
-                    name: name.to_string(),
+                    pattern,
                     is_mut,
                     value: e
                 }
             }
+
+        // Patterns which match any value of the right shape,
+        // names in them are bound only once
+        rule let_pattern() -> Pattern =
+            p:let_pattern_item() {?
+                let bindings = p.bindings();
+                let repeated = bindings
+                    .iter()
+                    .enumerate()
+                    .any(|(i, b)| bindings[..i].iter().any(|other| other.name == b.name));
+                if repeated {
+                    Err("every name to be bound once")
+                } else {
+                    Ok(p)
+                }
+            }
+
+        rule let_pattern_item() -> Pattern =
+            name:spanned_ident() { Pattern::Binding(name) }
+            / items:round_braced(<comma_separated(<let_pattern_item()>)>) {
+                Pattern::Tuple(items)
+            }
+            / fields:curly_braced(<comma_separated(<let_pattern_field()>)>) {
+                Pattern::Struct { name: None, fields }
+            }
+
+        // `x` is a shorthand for `x: x`
+        rule let_pattern_field() -> StructField =
+            name:spanned_ident()
+            pattern:(colon() p:let_pattern_item() { p })? {
+                StructField {
+                    name: name.name.clone(),
+                    pattern: pattern.unwrap_or(Pattern::Binding(name))
+                }
+            }
         rule constant() -> Stmt =
             const_kw()
-            name:ident()
+            name:spanned_ident()
             assign()
             e:expr() {
                 Stmt::Const {
-                    name: name.to_string(),
+                    name,
                     value: e
                 }
             }
@@ -368,7 +666,8 @@ peg::parser! {
 
     // Rule for parsing any definitions
     rule definition() -> Stmt =
-        function_definition()
+        extern_function_declaration()
+        / function_definition()
         / var_definition()
 
     //
@@ -377,110 +676,868 @@ peg::parser! {
 
     // Root rule for parsing whole source
     pub rule module() -> Module =
-        stmts:spaced(<stmts()>) {
+        first:spaced(<stmts()>)
+        // A stray closing brace is skipped with the rest of its line,
+        // parsing continues with the statements after it
+        rest:(stray:stray_brace() stmts:spaced(<stmts()>) { (stray, stmts) })* {
+            let mut statements = first;
+            for (stray, stmts) in rest {
+                statements.push(stray);
+                statements.extend(stmts);
+            }
             Module {
-                statements: stmts
+                statements
             }
         }
+
+        rule stray_brace() -> Stmt =
+            quiet!{
+                recovering() start:position!() "}" skipped_token()* end:position!() {
+                    Stmt::Expr(Expr::Error(Span::new(start, end)))
+                }
+            }
   }
 }
 
+/// Words which can't be used as names
+pub static KEYWORDS: &[&str] = &[
+    "if", "else", "let", "const", "fn", "extern", "async", "await", "import", "try", "catch",
+    "finally", "throw", "pub",
+];
+
+/// What the parser expects where a statement may end
+static STATEMENT_END: &str = "`;` or a line break";
+
+/// Dialect of the language a module is written in.
+///
+/// Syntax which changes meaning or stops being accepted only does so
+/// in a new version, so scripts written against an older one keep
+/// working when the parser pins that version.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+pub enum LanguageVersion {
+    /// Statements are separated by whitespace, `;` is optional, and
+    /// a `(` or `[` on a new line continues the previous expression
+    V1,
+    /// A line break ends the statement, statements on the same line
+    /// have to be separated with `;`
+    #[default]
+    V2,
+}
+
+impl LanguageVersion {
+    pub const LATEST: Self = Self::V2;
+
+    /// Parses the version as written in manifests, e.g. `"1"`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "1" => Some(Self::V1),
+            "2" => Some(Self::V2),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LanguageVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LanguageVersion::V1 => write!(f, "1"),
+            LanguageVersion::V2 => write!(f, "2"),
+        }
+    }
+}
+
+/// Parser of sky modules
+#[derive(Debug, Default, Clone)]
+pub struct Parser {
+    version: LanguageVersion,
+    /// Accept input which ends in the middle of a construct
+    partial: bool,
+    /// Skip over syntax errors, see [`Parser::parse_recovering`]
+    recover: bool,
+}
+
+/// Result of [`Parser::parse_partial`]
+#[derive(Debug, PartialEq)]
+pub struct PartialParse {
+    /// Everything that could be parsed, parts which are missing or can't
+    /// be parsed are `Expr::Error` nodes
+    pub module: Module,
+    pub status: ParseStatus,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseStatus {
+    Complete,
+    /// The input ends before the last construct is finished,
+    /// more input may complete it
+    Incomplete,
+    /// The input has an error which more input can't fix
+    Invalid(ParseError<LineCol>),
+}
+
+impl Parser {
+    pub const fn new() -> Self {
+        Self {
+            version: LanguageVersion::LATEST,
+            partial: false,
+            recover: false,
+        }
+    }
+
+    /// Parser for modules written against an older version of the language
+    pub const fn with_version(version: LanguageVersion) -> Self {
+        Self {
+            version,
+            partial: false,
+            recover: false,
+        }
+    }
+
+    pub fn version(&self) -> LanguageVersion {
+        self.version
+    }
+
+    pub fn parse(&self, source: &str) -> Result<Module, ParseError<LineCol>> {
+        parser::module(source, self)
+    }
+
+    /// Parses input which is still being written, as in the REPL or an editor.
+    ///
+    /// Unclosed brackets, missing operands and anything else cut off by
+    /// the end of the input are accepted. For invalid input, the module
+    /// contains what comes before the error.
+    pub fn parse_partial(&self, source: &str) -> PartialParse {
+        let err = match self.parse(source) {
+            Ok(module) => {
+                return PartialParse {
+                    module,
+                    status: ParseStatus::Complete,
+                }
+            }
+            Err(err) => err,
+        };
+
+        let mut partial = self.clone();
+        partial.partial = true;
+        if err.location.offset >= source.trim_end().len() {
+            let module = parser::module(source, &partial).unwrap_or_else(|_| Module::default());
+            return PartialParse {
+                module,
+                status: ParseStatus::Incomplete,
+            };
+        }
+        let module = parser::module(&source[..err.location.offset], &partial)
+            .unwrap_or_else(|_| Module::default());
+        PartialParse {
+            module,
+            status: ParseStatus::Invalid(err),
+        }
+    }
+
+    /// Parses the whole module even when it has syntax errors.
+    ///
+    /// Statements with errors are skipped up to the end of their line and
+    /// become `Expr::Error` nodes, so later passes can still look at the
+    /// rest of the module. Returns an error for every skipped part.
+    ///
+    /// With an older language version, syntax which the latest version
+    /// doesn't accept is reported as a deprecation warning.
+    pub fn parse_recovering(&self, source: &str) -> (Module, Vec<Diagnostic>) {
+        let mut recovering = self.clone();
+        recovering.recover = true;
+        let module = parser::module(source, &recovering).unwrap_or_default();
+
+        let mut skipped = Vec::new();
+        error_spans(&module.statements, &mut skipped);
+
+        // The exact error of every skipped part comes from parsing the
+        // source again with the parts already reported blanked out
+        let mut diagnostics = Vec::new();
+        let mut masked = source.to_string();
+        while let Err(err) = self.parse(&masked) {
+            let offset = err.location.offset;
+            diagnostics.push(syntax_error(&err));
+            let Some(i) = skipped.iter().position(|span| span.contains(offset)) else {
+                break;
+            };
+            let span = skipped.remove(i);
+            if span.start == span.end {
+                break;
+            }
+            let blank = masked[span.start..span.end]
+                .chars()
+                .map(|c| {
+                    if c == '\n' {
+                        "\n".to_string()
+                    } else {
+                        " ".repeat(c.len_utf8())
+                    }
+                })
+                .collect::<String>();
+            masked.replace_range(span.start..span.end, &blank);
+        }
+
+        if self.version < LanguageVersion::LATEST {
+            let latest = Parser::with_version(LanguageVersion::LATEST);
+            let (_, errors) = latest.parse_recovering(source);
+            let deprecated = errors
+                .into_iter()
+                .filter(|e| !diagnostics.iter().any(|d| d.span == e.span))
+                .map(|e| {
+                    Diagnostic::warning(
+                        format!(
+                            "deprecated syntax, an error from language version {}: {}",
+                            LanguageVersion::LATEST,
+                            e.message
+                        ),
+                        e.span,
+                    )
+                })
+                .collect::<Vec<_>>();
+            diagnostics.extend(deprecated);
+        }
+        (module, diagnostics)
+    }
+}
+
+// Spans of the statements skipped while recovering from errors
+fn error_spans(body: &[Stmt], spans: &mut Vec<Span>) {
+    for stmt in body {
+        match stmt {
+            Stmt::Expr(Expr::Error(span)) => spans.push(*span),
+            Stmt::Expr(Expr::If {
+                branches,
+                else_body,
+            }) => {
+                for branch in branches {
+                    error_spans(&branch.body.statements, spans);
+                }
+                if let Some(body) = else_body {
+                    error_spans(&body.statements, spans);
+                }
+            }
+            Stmt::Function { body, .. } => error_spans(&body.statements, spans),
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                error_spans(&body.statements, spans);
+                if let Some(catch) = catch {
+                    error_spans(&catch.body.statements, spans);
+                }
+                if let Some(finally) = finally {
+                    error_spans(&finally.statements, spans);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 pub fn parse(source: &str) -> Result<Module, ParseError<LineCol>> {
-    parser::module(source)
+    Parser::new().parse(source)
+}
+
+/// Diagnostic for a syntax error. When the statement before the error is
+/// complete, it asks for the statement to be ended instead of listing
+/// every token which could continue it.
+pub fn syntax_error(err: &ParseError<LineCol>) -> Diagnostic {
+    let offset = err.location.offset;
+    let message = if err.expected.tokens().any(|t| t == STATEMENT_END) {
+        format!("expected {} after the statement", STATEMENT_END)
+    } else {
+        format!("expected {}", err.expected)
+    };
+    Diagnostic::error(message, Span::new(offset, offset))
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::parser::ast::{Expr, FunctionParam, ImportedSymbol, Stmt, TypeUsage};
+    use crate::parser::ast::{
+        pattern::{Pattern, StructField},
+        Block, CallArgument, CatchClause, Expr, FunctionParam, Ident, IfBranch, ImportedSymbol,
+        Module, Span, Stmt, TypeUsage,
+    };
+
+    use super::{parse, parser, syntax_error, LanguageVersion, ParseStatus, Parser};
 
-    use super::parser;
+    static STRICT: Parser = Parser::new();
+
+    fn ident(name: &str, start: usize) -> Ident {
+        Ident::new(name, Span::new(start, start + name.len()))
+    }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn parse_float() {
-        assert_eq!(parser::float("3.14"), Ok(Expr::Float(3.14)))
+        assert_eq!(parser::float("3.14", &STRICT), Ok(Expr::Float(3.14)))
     }
 
     #[test]
     fn parse_int() {
-        assert_eq!(parser::int("2854"), Ok(Expr::Integer(2854)))
+        assert_eq!(parser::int("2854", &STRICT), Ok(Expr::Integer(2854)))
     }
 
     #[test]
     fn read_ident() {
-        assert_eq!(parser::ident("input12345"), Ok("input12345"));
-        assert_eq!(parser::ident("input"), Ok("input"));
+        assert_eq!(parser::ident("input12345", &STRICT), Ok("input12345"));
+        assert_eq!(parser::ident("input", &STRICT), Ok("input"));
     }
 
     #[test]
     fn string_literal() {
         assert_eq!(
-            parser::string_literal(r#""icyh\"nln\" ""#),
+            parser::string_literal(r#""icyh\"nln\" ""#, &STRICT),
             Ok("icyh\\\"nln\\\" ")
         )
     }
     #[test]
     fn import_stmt() {
         assert_eq!(
-            parser::import_stmt(r#"import { a as b, c} from "./path/to/file.sk""#),
+            parser::import_stmt(r#"import { a as b, c} from "./path/to/file.sk""#, &STRICT),
             Ok(Stmt::Import {
                 symbols: vec![
                     ImportedSymbol {
                         name: "a".to_string(),
-                        imported_as: Some("b".to_string())
+                        imported_as: Some("b".to_string()),
+                        span: Span::new(14, 15)
                     },
                     ImportedSymbol {
                         name: "c".to_string(),
-                        imported_as: None
+                        imported_as: None,
+                        span: Span::new(17, 18)
                     }
                 ],
-                path: "./path/to/file.sk".to_string()
+                namespace: None,
+                path: "./path/to/file.sk".to_string(),
+                is_pub: false
             })
-        )
+        );
+        assert_eq!(
+            parser::import_stmt(r#"pub import * as utils from "./utils.sk""#, &STRICT),
+            Ok(Stmt::Import {
+                symbols: Vec::new(),
+                namespace: Some(ident("utils", 16)),
+                path: "./utils.sk".to_string(),
+                is_pub: true
+            })
+        );
+        assert_eq!(
+            parser::import_stmt("import math::trig::{sin, cos as cosine}", &STRICT),
+            Ok(Stmt::Import {
+                symbols: vec![
+                    ImportedSymbol {
+                        name: "sin".to_string(),
+                        imported_as: None,
+                        span: Span::new(20, 23)
+                    },
+                    ImportedSymbol {
+                        name: "cos".to_string(),
+                        imported_as: Some("cosine".to_string()),
+                        span: Span::new(32, 38)
+                    }
+                ],
+                namespace: None,
+                path: "math/trig.sky".to_string(),
+                is_pub: false
+            })
+        );
+        assert_eq!(
+            parser::import_stmt("pub import utils as u", &STRICT),
+            Ok(Stmt::Import {
+                symbols: Vec::new(),
+                namespace: Some(ident("u", 20)),
+                path: "utils.sky".to_string(),
+                is_pub: true
+            })
+        );
+        assert!(parser::import_stmt("importutils as u", &STRICT).is_err());
+        assert!(parser::import_stmt("pubimport utils as u", &STRICT).is_err());
+        assert!(parser::import_stmt("import utils asu", &STRICT).is_err());
     }
 
     #[test]
     fn function_def_test() {
         assert_eq!(
-            parser::function_definition("fn foo(bar: Baz<Foo>) {}"),
+            parser::function_definition("fn foo(bar: Baz<Foo>) {}", &STRICT),
             Ok(Stmt::Function {
-                name: "foo".to_string(),
+                name: ident("foo", 3),
+                is_async: false,
                 params: vec![FunctionParam::new(
-                    "bar",
+                    ident("bar", 7),
                     TypeUsage {
                         name: "Baz".to_string(),
                         params: vec![TypeUsage::from_name("Foo")]
                     }
                 )],
                 ret_type: TypeUsage::from_name("Unit"),
-                body: Vec::new()
+                body: Block::new(Vec::new(), Span::new(22, 24))
             })
         )
     }
 
+    #[test]
+    fn extern_function_test() {
+        assert_eq!(
+            parser::extern_function_declaration("extern fn sqrt(x: float): float", &STRICT),
+            Ok(Stmt::ExternFunction {
+                name: ident("sqrt", 10),
+                is_async: false,
+                params: vec![FunctionParam::new(
+                    ident("x", 15),
+                    TypeUsage::from_name("float")
+                )],
+                ret_type: TypeUsage::from_name("float"),
+            })
+        );
+        assert_eq!(
+            parser::extern_function_declaration("extern fn exit()", &STRICT),
+            Ok(Stmt::ExternFunction {
+                name: ident("exit", 10),
+                is_async: false,
+                params: Vec::new(),
+                ret_type: TypeUsage::from_name("Unit"),
+            })
+        );
+        assert!(parser::extern_function_declaration("externfn exit()", &STRICT).is_err());
+        assert!(parser::function_definition("asyncfn f() {}", &STRICT).is_err());
+    }
+
+    #[test]
+    fn async_function_test() {
+        assert_eq!(
+            parser::function_definition(
+                "async fn load(url: string): string = await fetch(url).text()",
+                &STRICT
+            ),
+            Ok(Stmt::Function {
+                name: ident("load", 9),
+                is_async: true,
+                params: vec![FunctionParam::new(
+                    ident("url", 14),
+                    TypeUsage::from_name("string")
+                )],
+                ret_type: TypeUsage::from_name("string"),
+                body: Block::new(
+                    vec![Stmt::Expr(Expr::Await(Box::new(Expr::Call {
+                        target: Box::new(Expr::DotAccess {
+                            target: Box::new(Expr::Call {
+                                target: Box::new(Expr::Ident(ident("fetch", 43))),
+                                arguments: vec![CallArgument {
+                                    name: None,
+                                    expr: Expr::Ident(ident("url", 49))
+                                }]
+                            }),
+                            name: ident("text", 54)
+                        }),
+                        arguments: Vec::new()
+                    })))],
+                    Span::new(37, 60)
+                )
+            })
+        );
+        assert_eq!(
+            parser::extern_function_declaration("extern async fn sleep(ms: int)", &STRICT),
+            Ok(Stmt::ExternFunction {
+                name: ident("sleep", 16),
+                is_async: true,
+                params: vec![FunctionParam::new(
+                    ident("ms", 22),
+                    TypeUsage::from_name("int")
+                )],
+                ret_type: TypeUsage::from_name("Unit"),
+            })
+        );
+    }
+
+    #[test]
+    fn postfix_binds_tighter_than_operators() {
+        assert_eq!(
+            parser::expr("1 + f(2)", &STRICT),
+            Ok(Expr::bin_add(
+                Expr::Integer(1),
+                Expr::Call {
+                    target: Box::new(Expr::Ident(ident("f", 4))),
+                    arguments: vec![CallArgument {
+                        name: None,
+                        expr: Expr::Integer(2)
+                    }]
+                }
+            ))
+        );
+        assert_eq!(
+            parser::expr("await a.b * awaited[0]", &STRICT),
+            Ok(Expr::bin_mul(
+                Expr::Await(Box::new(Expr::DotAccess {
+                    target: Box::new(Expr::Ident(ident("a", 6))),
+                    name: ident("b", 8)
+                })),
+                Expr::BracketAccess {
+                    target: Box::new(Expr::Ident(ident("awaited", 12))),
+                    expr: Box::new(Expr::Integer(0))
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn cast_test() {
+        assert_eq!(
+            parser::expr("a * b as i64 as f64", &STRICT),
+            Ok(Expr::bin_mul(
+                Expr::Ident(ident("a", 0)),
+                Expr::Cast {
+                    expr: Box::new(Expr::Cast {
+                        expr: Box::new(Expr::Ident(ident("b", 4))),
+                        r#type: TypeUsage::from_name("i64")
+                    }),
+                    r#type: TypeUsage::from_name("f64")
+                }
+            ))
+        );
+        assert_eq!(
+            parser::module("let y = x\nasync fn f() {}", &STRICT).map(|m| m.statements.len()),
+            Ok(2)
+        );
+    }
+
+    #[test]
+    fn try_stmt_test() {
+        assert_eq!(
+            parser::try_stmt(
+                "try { risky() } catch (e) { log(e) } finally { close() }",
+                &STRICT
+            ),
+            Ok(Stmt::Try {
+                body: Block::new(
+                    vec![Stmt::Expr(Expr::Call {
+                        target: Box::new(Expr::Ident(ident("risky", 6))),
+                        arguments: Vec::new()
+                    })],
+                    Span::new(4, 15)
+                ),
+                catch: Some(CatchClause {
+                    binding: Some(ident("e", 23)),
+                    body: Block::new(
+                        vec![Stmt::Expr(Expr::Call {
+                            target: Box::new(Expr::Ident(ident("log", 28))),
+                            arguments: vec![CallArgument {
+                                name: None,
+                                expr: Expr::Ident(ident("e", 32))
+                            }]
+                        })],
+                        Span::new(26, 36)
+                    )
+                }),
+                finally: Some(Block::new(
+                    vec![Stmt::Expr(Expr::Call {
+                        target: Box::new(Expr::Ident(ident("close", 47))),
+                        arguments: Vec::new()
+                    })],
+                    Span::new(45, 56)
+                ))
+            })
+        );
+        assert_eq!(
+            parser::try_stmt("try {} catch {}", &STRICT),
+            Ok(Stmt::Try {
+                body: Block::new(Vec::new(), Span::new(4, 6)),
+                catch: Some(CatchClause {
+                    binding: None,
+                    body: Block::new(Vec::new(), Span::new(13, 15))
+                }),
+                finally: None
+            })
+        );
+        assert!(parser::try_stmt("try {}", &STRICT).is_err());
+    }
+
+    #[test]
+    fn throw_stmt_test() {
+        assert_eq!(
+            parser::throw_stmt(r#"throw "boom""#, &STRICT),
+            Ok(Stmt::Throw {
+                value: Expr::String("boom".to_string()),
+                span: Span::new(0, 12)
+            })
+        );
+        assert_eq!(
+            parser::module("throwable", &STRICT),
+            Ok(Module {
+                statements: vec![Stmt::Expr(Expr::Ident(ident("throwable", 0)))]
+            })
+        );
+    }
+
     #[test]
     fn var_definition_test() {
         assert_eq!(
-            parser::var_definition("let a = 1"),
+            parser::var_definition("let a = 1", &STRICT),
             Ok(Stmt::Var {
-                name: "a".to_string(),
+                pattern: Pattern::Binding(ident("a", 4)),
                 is_mut: false,
                 value: Expr::Integer(1)
             })
         );
         assert_eq!(
-            parser::var_definition("let mut a = 1"),
+            parser::var_definition("let mut a = 1", &STRICT),
             Ok(Stmt::Var {
-                name: "a".to_string(),
+                pattern: Pattern::Binding(ident("a", 8)),
                 is_mut: true,
                 value: Expr::Integer(1)
             })
         );
         assert_eq!(
-            parser::var_definition("const a = 1"),
+            parser::var_definition("const a = 1", &STRICT),
             Ok(Stmt::Const {
-                name: "a".to_string(),
+                name: ident("a", 6),
                 value: Expr::Integer(1)
             })
         );
     }
+
+    #[test]
+    fn let_patterns() {
+        assert_eq!(
+            parser::var_definition("let mut ((a, b), { x, y: py }) = p", &STRICT),
+            Ok(Stmt::Var {
+                pattern: Pattern::Tuple(vec![
+                    Pattern::Tuple(vec![
+                        Pattern::Binding(ident("a", 10)),
+                        Pattern::Binding(ident("b", 13))
+                    ]),
+                    Pattern::Struct {
+                        name: None,
+                        fields: vec![
+                            StructField {
+                                name: "x".to_string(),
+                                pattern: Pattern::Binding(ident("x", 19))
+                            },
+                            StructField {
+                                name: "y".to_string(),
+                                pattern: Pattern::Binding(ident("py", 25))
+                            }
+                        ]
+                    }
+                ]),
+                is_mut: true,
+                value: Expr::Ident(ident("p", 33))
+            })
+        );
+        assert!(parser::var_definition("let (a, { a }) = p", &STRICT).is_err());
+        assert!(parser::var_definition("let (a, 1) = p", &STRICT).is_err());
+    }
+
+    #[test]
+    fn else_if_chain_is_flat() {
+        assert_eq!(
+            parser::if_expr("if a { 1 } else if b { 2 } else { 3 }", &STRICT),
+            Ok(Expr::If {
+                branches: vec![
+                    IfBranch {
+                        condition: Expr::Ident(ident("a", 3)),
+                        body: Block::new(vec![Stmt::Expr(Expr::Integer(1))], Span::new(5, 10))
+                    },
+                    IfBranch {
+                        condition: Expr::Ident(ident("b", 19)),
+                        body: Block::new(vec![Stmt::Expr(Expr::Integer(2))], Span::new(21, 26))
+                    }
+                ],
+                else_body: Some(Block::new(
+                    vec![Stmt::Expr(Expr::Integer(3))],
+                    Span::new(32, 37)
+                ))
+            })
+        );
+    }
+
+    #[test]
+    fn else_belongs_to_closest_if() {
+        let dump = |source: &str| parser::if_expr(source, &STRICT).unwrap().dump();
+        assert_eq!(
+            dump("if a { if b { 1 } else { 2 } }"),
+            "(cond\n  (a\n    (cond\n      (b\n        1)\n      (else\n        2))))"
+        );
+        assert_eq!(
+            dump("if a { if b { 1 } } else { 2 }"),
+            "(cond\n  (a\n    (cond\n      (b\n        1)))\n  (else\n    2))"
+        );
+        assert_eq!(
+            dump("if a { 1 } else { if b { 2 } else { 3 } }"),
+            "(cond\n  (a\n    1)\n  (else\n    (cond\n      (b\n        2)\n      (else\n        3))))"
+        );
+    }
+
+    #[test]
+    fn if_without_braces() {
+        let err = parser::if_expr("if a 1", &STRICT).unwrap_err();
+        assert_eq!(err.location.offset, 5);
+        assert!(err.expected.tokens().any(|t| t == "\"{\""));
+
+        let err = parser::module("fn f() {\n    if a { 1 else { 2 }\n}", &STRICT).unwrap_err();
+        assert_eq!((err.location.line, err.location.column), (2, 14));
+        assert!(err.expected.tokens().any(|t| t == "\"}\""));
+
+        let err = parser::module("if a { 1 } else", &STRICT).unwrap_err();
+        assert_eq!(err.location.offset, 15);
+        assert!(err.expected.tokens().any(|t| t == "\"{\""));
+    }
+
+    #[test]
+    fn line_breaks_end_statements() {
+        assert_eq!(
+            parse("let a = f\n(b)\nlet c = a\n    + b\n    .len; let d = c[0]\n(e)")
+                .unwrap()
+                .dump(),
+            "(module\n  (let a f)\n  b\n  (let c (+ a (. b len)))\n  (let d ([] c 0))\n  e)\n"
+        );
+        assert_eq!(parse("f(); g();").map(|m| m.statements.len()), Ok(2));
+    }
+
+    #[test]
+    fn missing_semicolon() {
+        let error = |source: &str| syntax_error(&parse(source).unwrap_err()).render(source);
+        assert_eq!(
+            error("let a = 1 let b = 2"),
+            "error at 1:11: expected `;` or a line break after the statement"
+        );
+        assert_eq!(
+            error("fn f() {\n    g(a) h(b)\n}"),
+            "error at 2:10: expected `;` or a line break after the statement"
+        );
+        assert!(!error("let a = 1 +").contains("line break"));
+    }
+
+    #[test]
+    fn language_version_1() {
+        let v1 = Parser::with_version(LanguageVersion::V1);
+        assert_eq!(
+            v1.parse("let a = f\n(b) let c = a\n[0]").unwrap().dump(),
+            "(module\n  (let a (call f b))\n  (let c ([] a 0)))\n"
+        );
+
+        let source = "let a = 1 let b = 2\nfn f() {\n    g() h()\n}";
+        let (module, diagnostics) = v1.parse_recovering(source);
+        assert_eq!(module.statements.len(), 3);
+        let rendered = diagnostics
+            .iter()
+            .map(|d| d.render(source))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rendered,
+            vec![
+                "warning at 1:11: deprecated syntax, an error from language version 2: expected `;` or a line break after the statement",
+                "warning at 3:9: deprecated syntax, an error from language version 2: expected `;` or a line break after the statement",
+            ]
+        );
+
+        // Errors in both versions are only reported once
+        let (_, diagnostics) = v1.parse_recovering("let = 1");
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn partial_input() {
+        let partial = |source: &str| {
+            let result = Parser::new().parse_partial(source);
+            (result.status, result.module.dump())
+        };
+        assert_eq!(
+            partial("let a = 1"),
+            (ParseStatus::Complete, "(module\n  (let a 1))\n".to_string())
+        );
+        assert_eq!(
+            partial("fn f() {\n    let a = 1 +"),
+            (
+                ParseStatus::Incomplete,
+                "(module\n  (fn f () Unit\n    (let a (+ 1 (error)))))\n".to_string()
+            )
+        );
+        assert_eq!(
+            partial("if a {\n    g(x, "),
+            (
+                ParseStatus::Incomplete,
+                "(module\n  (cond\n    (a\n      (call g x (error)))))\n".to_string()
+            )
+        );
+        assert_eq!(
+            partial("x.\n"),
+            (ParseStatus::Incomplete, "(module\n  (. x ))\n".to_string())
+        );
+        assert_eq!(
+            partial("let a = 1\nlet b"),
+            (
+                ParseStatus::Incomplete,
+                "(module\n  (let a 1)\n  (error))\n".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn partial_input_with_errors() {
+        let result = Parser::new().parse_partial("let a = 1\nfn f() { g(a)) }\nlet b = 2");
+        assert_eq!(
+            result.module.dump(),
+            "(module\n  (let a 1)\n  (fn f () Unit\n    (call g a)))\n"
+        );
+        let ParseStatus::Invalid(err) = result.status else {
+            panic!("the input should be invalid");
+        };
+        assert_eq!((err.location.line, err.location.column), (2, 14));
+    }
+
+    #[test]
+    fn recovers_from_syntax_errors() {
+        let source = "let a = 1 )\nfn f() {\n    let = 2\n    g(a)\n}\nlet b = 3";
+        let (module, diagnostics) = Parser::new().parse_recovering(source);
+        assert_eq!(
+            module.dump(),
+            "(module\n  (let a 1)\n  (error)\n  (fn f () Unit\n    (error)\n    (call g a))\n  (let b 3))\n"
+        );
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| d.render(source))
+                .collect::<Vec<_>>(),
+            vec![
+                "error at 1:11: expected `;` or a line break after the statement",
+                "error at 3:9: expected one of \"(\", \"mut\", \"{\", ['a'..='z' | 'A'..='Z']",
+            ]
+        );
+
+        let (module, diagnostics) = Parser::new().parse_recovering("let a = 1\nlet b = a +");
+        assert_eq!(
+            module.dump(),
+            "(module\n  (let a 1)\n  (let b (+ a (error))))\n"
+        );
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn recovers_after_stray_closing_braces() {
+        let source = "fn f() {\n    g()\n}\n}\nfn h() {}\nlet z = 1\nlet = 3";
+        let (module, diagnostics) = Parser::new().parse_recovering(source);
+        assert_eq!(
+            module.dump(),
+            "(module\n  (fn f () Unit\n    (call g))\n  (error)\n  (fn h () Unit)\n  (let z 1)\n  (error))\n"
+        );
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| d.render(source))
+                .collect::<Vec<_>>(),
+            vec![
+                "error at 4:1: expected one of \"(\", \";\", \"\\\"\", \"async\", \"await\", \"const\", \"extern\", \"fn\", \"if\", \"import\", \"let\", \"pub\", \"throw\", \"try\", EOF, ['0'..='9'], ['a'..='z' | 'A'..='Z']",
+                "error at 7:5: expected one of \"(\", \"mut\", \"{\", ['a'..='z' | 'A'..='Z']",
+            ]
+        );
+
+        let (module, diagnostics) = Parser::new().parse_recovering("let a = 1\n} g()\nlet b = 2");
+        assert_eq!(
+            module.dump(),
+            "(module\n  (let a 1)\n  (error)\n  (let b 2))\n"
+        );
+        assert_eq!(diagnostics.len(), 1);
+    }
 }
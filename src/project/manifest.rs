@@ -0,0 +1,440 @@
+//! `sky.toml` project manifest.
+//!
+//! The manifest is written in the subset of TOML needed to describe a
+//! package: tables, dotted table headers, strings without escapes,
+//! integers, booleans, arrays and inline tables.
+//!
+//! ```toml
+//! [package]
+//! name = "app"
+//! entry = "src/main.sky"
+//! source-dirs = ["src"]
+//! language-version = "2"
+//!
+//! [dependencies]
+//! utils = { path = "../utils" }
+//!
+//! [profile.release]
+//! out-dir = "build/release"
+//! deny-warnings = true
+//! ```
+
+use std::{collections::BTreeMap, error::Error, fmt};
+
+use peg::{error::ParseError, str::LineCol};
+
+use crate::parser::LanguageVersion;
+
+#[derive(Debug, PartialEq, Clone)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Bool(bool),
+    Array(Vec<Value>),
+    Table(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::String(_) => "a string",
+            Value::Integer(_) => "an integer",
+            Value::Bool(_) => "a boolean",
+            Value::Array(_) => "an array",
+            Value::Table(_) => "a table",
+        }
+    }
+}
+
+// Header path and entries of a table
+type Table = (Vec<String>, Vec<(String, Value)>);
+
+enum Line {
+    Header(Vec<String>),
+    Entry(String, Value),
+    Blank,
+}
+
+peg::parser! {
+    grammar toml() for str {
+        rule ws() = [' ' | '\t']*
+        rule comment() = "#" (!['\n'] [_])*
+        rule nl() = "\r"? "\n"
+        // Whitespace, comments and newlines inside arrays
+        rule wsnl() = (ws() comment()? nl())* ws()
+        rule eol() = ws() comment()? (nl() / ![_])
+
+        rule bare_key() -> String =
+            k:$(['a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-']+) { k.to_string() }
+        rule key() -> String = bare_key() / string()
+
+        rule string() -> String =
+            "\"" s:$((!['"' | '\\' | '\n'] [_])*) "\"" { s.to_string() }
+
+        rule integer() -> i64 =
+            i:$("-"? ['0'..='9']+) {? i.parse().or(Err("integer")) }
+
+        rule value() -> Value =
+            s:string() { Value::String(s) }
+            / "true" { Value::Bool(true) }
+            / "false" { Value::Bool(false) }
+            / i:integer() { Value::Integer(i) }
+            / "[" wsnl() items:(value() ** (wsnl() "," wsnl())) wsnl() ","? wsnl() "]" {
+                Value::Array(items)
+            }
+            / "{" ws() entries:(entry() ** (ws() "," ws())) ws() "}" {
+                Value::Table(entries)
+            }
+
+        rule entry() -> (String, Value) =
+            k:key() ws() "=" ws() v:value() { (k, v) }
+
+        rule header() -> Vec<String> =
+            "[" ws() path:(key() ++ (ws() "." ws())) ws() "]" { path }
+
+        rule line() -> Line =
+            ws() h:header() eol() { Line::Header(h) }
+            / ws() e:entry() eol() { Line::Entry(e.0, e.1) }
+            / ws() comment()? nl() { Line::Blank }
+
+        pub rule document() -> Vec<Line> =
+            lines:line()* ws() comment()? { lines }
+    }
+}
+
+#[derive(Debug)]
+pub enum ManifestError {
+    Syntax(ParseError<LineCol>),
+    Invalid(String),
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::Syntax(err) => write!(f, "invalid manifest: {}", err),
+            ManifestError::Invalid(msg) => write!(f, "invalid manifest: {}", msg),
+        }
+    }
+}
+
+impl Error for ManifestError {}
+
+fn invalid<T>(msg: String) -> Result<T, ManifestError> {
+    Err(ManifestError::Invalid(msg))
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Dependency {
+    /// Directory of the dependency, relative to the manifest
+    pub path: String,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Profile {
+    /// Directory the compiled modules are written to, relative to the manifest
+    pub out_dir: String,
+    /// Fail the build when there are warnings
+    pub deny_warnings: bool,
+}
+
+impl Profile {
+    fn default_for(name: &str) -> Self {
+        Self {
+            out_dir: format!("build/{}", name),
+            deny_warnings: name == "release",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Manifest {
+    pub name: String,
+    pub version: Option<String>,
+    /// Module run by `sky run`, relative to the manifest
+    pub entry: String,
+    /// Directories with the modules of the package, relative to the manifest
+    pub source_dirs: Vec<String>,
+    /// Version of the language the modules are written in, the latest by default
+    pub language_version: LanguageVersion,
+    pub dependencies: BTreeMap<String, Dependency>,
+    /// Build profiles by name, `dev` and `release` always exist
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl Manifest {
+    pub fn parse(source: &str) -> Result<Self, ManifestError> {
+        let lines = toml::document(source).map_err(ManifestError::Syntax)?;
+
+        let mut tables: Vec<Table> = vec![(Vec::new(), Vec::new())];
+        for line in lines {
+            match line {
+                Line::Header(path) => {
+                    if tables.iter().any(|(p, _)| *p == path) {
+                        return invalid(format!("table [{}] is defined twice", path.join(".")));
+                    }
+                    tables.push((path, Vec::new()));
+                }
+                Line::Entry(key, value) => {
+                    let (path, entries) = tables.last_mut().expect("root table always exists");
+                    if entries.iter().any(|(k, _)| *k == key) {
+                        return invalid(format!(
+                            "key `{}` is defined twice",
+                            dotted(&path.join("."), &key)
+                        ));
+                    }
+                    entries.push((key, value));
+                }
+                Line::Blank => {}
+            }
+        }
+
+        let mut package = None;
+        let mut dependencies = BTreeMap::new();
+        let mut profiles = BTreeMap::from([
+            ("dev".to_string(), Profile::default_for("dev")),
+            ("release".to_string(), Profile::default_for("release")),
+        ]);
+        for (path, entries) in tables {
+            match path.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+                [] if entries.is_empty() => {}
+                ["package"] => package = Some(entries),
+                ["dependencies"] => {
+                    for (name, value) in entries {
+                        let dep = parse_dependency(&name, value)?;
+                        dependencies.insert(name, dep);
+                    }
+                }
+                ["profile", name] => {
+                    let profile = parse_profile(name, entries)?;
+                    profiles.insert(name.to_string(), profile);
+                }
+                [] => return invalid(format!("unknown key `{}`", entries[0].0)),
+                _ => return invalid(format!("unknown table [{}]", path.join("."))),
+            }
+        }
+
+        let Some(package) = package else {
+            return invalid("missing [package] table".to_string());
+        };
+        let mut name = None;
+        let mut version = None;
+        let mut entry = "src/main.sky".to_string();
+        let mut source_dirs = vec!["src".to_string()];
+        let mut language_version = LanguageVersion::LATEST;
+        for (key, value) in package {
+            let path = dotted("package", &key);
+            match key.as_str() {
+                "name" => name = Some(expect_string(&path, value)?),
+                "version" => version = Some(expect_string(&path, value)?),
+                "entry" => entry = expect_string(&path, value)?,
+                "source-dirs" => {
+                    let Value::Array(dirs) = value else {
+                        return invalid(format!("`{}` must be an array", path));
+                    };
+                    source_dirs = dirs
+                        .into_iter()
+                        .map(|dir| expect_string(&path, dir))
+                        .collect::<Result<_, _>>()?;
+                }
+                "language-version" => {
+                    let version = expect_string(&path, value)?;
+                    let Some(version) = LanguageVersion::from_name(&version) else {
+                        return invalid(format!(
+                            "unknown language version `{}` in `{}`, supported versions: 1, 2",
+                            version, path
+                        ));
+                    };
+                    language_version = version;
+                }
+                _ => return invalid(format!("unknown key `{}`", path)),
+            }
+        }
+        let Some(name) = name else {
+            return invalid("missing `package.name`".to_string());
+        };
+
+        Ok(Self {
+            name,
+            version,
+            entry,
+            source_dirs,
+            language_version,
+            dependencies,
+            profiles,
+        })
+    }
+}
+
+fn dotted(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn expect_string(path: &str, value: Value) -> Result<String, ManifestError> {
+    match value {
+        Value::String(s) => Ok(s),
+        value => invalid(format!(
+            "`{}` must be a string, found {}",
+            path,
+            value.kind()
+        )),
+    }
+}
+
+fn expect_bool(path: &str, value: Value) -> Result<bool, ManifestError> {
+    match value {
+        Value::Bool(b) => Ok(b),
+        value => invalid(format!(
+            "`{}` must be a boolean, found {}",
+            path,
+            value.kind()
+        )),
+    }
+}
+
+fn parse_dependency(name: &str, value: Value) -> Result<Dependency, ManifestError> {
+    let path = format!("dependencies.{}", name);
+    let Value::Table(entries) = value else {
+        return invalid(format!(
+            "`{}` must be a table like {{ path = \"../{}\" }}",
+            path, name
+        ));
+    };
+    let mut dep_path = None;
+    for (key, value) in entries {
+        match key.as_str() {
+            "path" => dep_path = Some(expect_string(&dotted(&path, &key), value)?),
+            _ => return invalid(format!("unknown key `{}`", dotted(&path, &key))),
+        }
+    }
+    match dep_path {
+        Some(path) => Ok(Dependency { path }),
+        None => invalid(format!("missing `{}.path`", path)),
+    }
+}
+
+fn parse_profile(name: &str, entries: Vec<(String, Value)>) -> Result<Profile, ManifestError> {
+    let mut profile = Profile::default_for(name);
+    for (key, value) in entries {
+        let path = dotted(&format!("profile.{}", name), &key);
+        match key.as_str() {
+            "out-dir" => profile.out_dir = expect_string(&path, value)?,
+            "deny-warnings" => profile.deny_warnings = expect_bool(&path, value)?,
+            _ => return invalid(format!("unknown key `{}`", path)),
+        }
+    }
+    Ok(profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::parser::LanguageVersion;
+
+    use super::{Dependency, Manifest, Profile};
+
+    #[test]
+    fn full_manifest() {
+        let manifest = Manifest::parse(
+            r#"
+# The application
+[package]
+name = "app"
+version = "0.1.0" # trailing comment
+entry = "src/app.sky"
+source-dirs = [
+    "src",
+    "generated",
+]
+language-version = "1"
+
+[dependencies]
+utils = { path = "../utils" }
+
+[profile.release]
+out-dir = "dist"
+
+[profile.test]
+deny-warnings = true
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            manifest,
+            Manifest {
+                name: "app".to_string(),
+                version: Some("0.1.0".to_string()),
+                entry: "src/app.sky".to_string(),
+                source_dirs: vec!["src".to_string(), "generated".to_string()],
+                language_version: LanguageVersion::V1,
+                dependencies: BTreeMap::from([(
+                    "utils".to_string(),
+                    Dependency {
+                        path: "../utils".to_string()
+                    }
+                )]),
+                profiles: BTreeMap::from([
+                    (
+                        "dev".to_string(),
+                        Profile {
+                            out_dir: "build/dev".to_string(),
+                            deny_warnings: false
+                        }
+                    ),
+                    (
+                        "release".to_string(),
+                        Profile {
+                            out_dir: "dist".to_string(),
+                            deny_warnings: true
+                        }
+                    ),
+                    (
+                        "test".to_string(),
+                        Profile {
+                            out_dir: "build/test".to_string(),
+                            deny_warnings: true
+                        }
+                    ),
+                ]),
+            }
+        );
+    }
+
+    #[test]
+    fn defaults() {
+        let manifest = Manifest::parse("[package]\nname = \"app\"").unwrap();
+        assert_eq!(manifest.entry, "src/main.sky");
+        assert_eq!(manifest.source_dirs, vec!["src".to_string()]);
+        assert_eq!(manifest.language_version, LanguageVersion::LATEST);
+        assert!(manifest.dependencies.is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        let error = |source: &str| Manifest::parse(source).unwrap_err().to_string();
+
+        assert_eq!(error(""), "invalid manifest: missing [package] table");
+        assert_eq!(
+            error("[package]\nname = 1"),
+            "invalid manifest: `package.name` must be a string, found an integer"
+        );
+        assert_eq!(
+            error("[package]\nname = \"a\"\nmain = \"x\""),
+            "invalid manifest: unknown key `package.main`"
+        );
+        assert_eq!(
+            error("[package]\nname = \"a\"\n[dependencies]\nutils = \"../utils\""),
+            "invalid manifest: `dependencies.utils` must be a table like { path = \"../utils\" }"
+        );
+        assert_eq!(
+            error("[package]\nname = \"a\"\nlanguage-version = \"3\""),
+            "invalid manifest: unknown language version `3` in `package.language-version`, supported versions: 1, 2"
+        );
+        assert!(error("[package\nname = \"a\"").starts_with("invalid manifest: error at 1:9"));
+    }
+}
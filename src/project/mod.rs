@@ -0,0 +1,651 @@
+//! Packages described by a `sky.toml` manifest and building all of their modules.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    env, fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    analyzer::{
+        analyze,
+        consts::{eval_consts, ImportedConsts},
+        imports::{exports, ImportedExports},
+        numeric::truncate_integer_division,
+    },
+    compiler::gen,
+    error::Severity,
+    parser::{
+        ast::{Module, Stmt},
+        LanguageVersion, Parser,
+    },
+};
+
+pub use self::manifest::{Dependency, Manifest, ManifestError, Profile};
+
+mod manifest;
+
+pub static MANIFEST_NAME: &str = "sky.toml";
+
+/// File extensions of sky modules
+pub static EXTENSIONS: [&str; 2] = ["sky", "sk"];
+
+#[derive(Debug)]
+pub struct Package {
+    /// Directory containing the manifest
+    pub root: PathBuf,
+    pub manifest: Manifest,
+    pub dependencies: BTreeMap<String, Package>,
+}
+
+impl Package {
+    /// Loads the package with its manifest in `root`, along with its dependencies
+    pub fn load(root: &Path) -> Result<Self, String> {
+        Self::load_nested(root, &mut Vec::new())
+    }
+
+    fn load_nested(root: &Path, loading: &mut Vec<PathBuf>) -> Result<Self, String> {
+        let manifest_path = root.join(MANIFEST_NAME);
+        let source = fs::read_to_string(&manifest_path)
+            .map_err(|err| format!("{}: {}", manifest_path.display(), err))?;
+        let manifest = Manifest::parse(&source)
+            .map_err(|err| format!("{}: {}", manifest_path.display(), err))?;
+
+        let root = fs::canonicalize(root).map_err(|err| format!("{}: {}", root.display(), err))?;
+        if loading.contains(&root) {
+            return Err(format!(
+                "{}: package `{}` depends on itself",
+                manifest_path.display(),
+                manifest.name
+            ));
+        }
+        loading.push(root.clone());
+        let mut dependencies = BTreeMap::new();
+        for (name, dep) in &manifest.dependencies {
+            let package = Self::load_nested(&root.join(&dep.path), loading)?;
+            dependencies.insert(name.clone(), package);
+        }
+        loading.pop();
+
+        Ok(Self {
+            root,
+            manifest,
+            dependencies,
+        })
+    }
+
+    /// Finds the closest `sky.toml` in `dir` or its parents
+    pub fn discover(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .find(|d| d.join(MANIFEST_NAME).is_file())
+            .map(Path::to_path_buf)
+    }
+
+    /// Loads the package containing the current directory
+    pub fn current() -> Result<Self, String> {
+        let cwd = env::current_dir().map_err(|err| err.to_string())?;
+        match Self::discover(&cwd) {
+            Some(root) => Self::load(&root),
+            None => Err(format!(
+                "could not find {} in {} or any parent directory",
+                MANIFEST_NAME,
+                cwd.display()
+            )),
+        }
+    }
+
+    /// Every module of the package, in a stable order
+    pub fn modules(&self) -> io::Result<Vec<PathBuf>> {
+        let mut modules = Vec::new();
+        for dir in &self.manifest.source_dirs {
+            collect_modules(&self.root.join(dir), &mut modules)?;
+        }
+        modules.sort();
+        modules.dedup();
+        Ok(modules)
+    }
+
+    /// File imported with `import` from the module at `from`.
+    ///
+    /// Paths starting with the name of a dependency point into the first
+    /// source directory of that dependency, other paths are relative to
+    /// the importing module.
+    pub fn resolve_import(&self, from: &Path, import: &str) -> Option<PathBuf> {
+        let dependency = import
+            .split_once('/')
+            .and_then(|(name, rest)| Some((self.dependencies.get(name)?, rest)));
+        match dependency {
+            Some((dep, rest)) => {
+                let dir = dep.manifest.source_dirs.first()?;
+                Some(normalize(&dep.root.join(dir).join(rest)))
+            }
+            None => Some(normalize(&from.parent()?.join(import))),
+        }
+    }
+
+    /// Package which owns the module at `path`
+    fn owner(&self, path: &Path) -> &Package {
+        self.dependencies
+            .values()
+            .map(|dep| dep.owner(path))
+            .find(|p| path.starts_with(&p.root))
+            .unwrap_or(self)
+    }
+
+    /// File the module at `path` is compiled to, dependencies are
+    /// placed into `deps/<name>` inside of the output directory. A
+    /// package shared by several dependencies is placed below the first
+    /// of them.
+    pub fn output_path(&self, out_dir: &Path, path: &Path) -> Option<PathBuf> {
+        for (name, dep) in &self.dependencies {
+            if path.starts_with(&dep.owner(path).root) {
+                return dep.output_path(&out_dir.join("deps").join(name), path);
+            }
+        }
+        let relative = self
+            .manifest
+            .source_dirs
+            .iter()
+            .find_map(|dir| path.strip_prefix(self.root.join(dir)).ok())?;
+        Some(out_dir.join(relative).with_extension("mjs"))
+    }
+}
+
+fn collect_modules(dir: &Path, modules: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_modules(&path, modules)?;
+        } else if is_module(&path) {
+            modules.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_module(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| EXTENSIONS.contains(&e))
+}
+
+// Removes `.` and `..` components without touching the file system
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// Path to `to` relative to the directory `from`, as used in JS imports
+fn relative_import(from: &Path, to: &Path) -> String {
+    let from = from.components().collect::<Vec<_>>();
+    let to = to.components().collect::<Vec<_>>();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut parts = vec![".".to_string()];
+    parts.extend((common..from.len()).map(|_| "..".to_string()));
+    parts.extend(
+        to[common..]
+            .iter()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned()),
+    );
+    if parts.len() > 1 && parts[1] == ".." {
+        parts.remove(0);
+    }
+    parts.join("/")
+}
+
+/// Import path in the compiled code of a single module at `from`, which
+/// expects the imported modules to be compiled next to their sources.
+/// `from` should be absolute, so `..` can be resolved.
+pub fn single_file_import(from: &Path, import: &str) -> String {
+    let dir = from.parent().unwrap_or(from);
+    relative_import(dir, &normalize(&dir.join(import)).with_extension("mjs"))
+}
+
+/// File imported with `import` from the module at the path, along with the
+/// language version of the package it belongs to
+pub type ResolveImport<'a> = dyn Fn(&Path, &str) -> Option<(PathBuf, LanguageVersion)> + 'a;
+
+/// Evaluates the constants of every module imported by `module` at `path`.
+///
+/// Modules that can't be resolved, read or parsed are skipped, as are import cycles.
+pub fn imported_consts(
+    path: &Path,
+    module: &Module,
+    resolve: &ResolveImport<'_>,
+    visiting: &mut Vec<PathBuf>,
+) -> ImportedConsts {
+    let mut imported = ImportedConsts::new();
+    visiting.push(path.to_path_buf());
+    for stmt in &module.statements {
+        let Stmt::Import { path: import, .. } = stmt else {
+            continue;
+        };
+        let Some((file, version)) = resolve(path, import) else {
+            continue;
+        };
+        if visiting.contains(&file) {
+            continue;
+        }
+        let Ok(source) = fs::read_to_string(&file) else {
+            continue;
+        };
+        let Ok(module) = Parser::with_version(version).parse(&source) else {
+            continue;
+        };
+        let nested = imported_consts(&file, &module, resolve, visiting);
+        imported.insert(import.clone(), eval_consts(&module, &nested).0);
+    }
+    visiting.pop();
+    imported
+}
+
+/// Names exported by every module imported by `module` at `path`.
+///
+/// Modules that can't be resolved, read or parsed are skipped.
+pub fn imported_exports(
+    path: &Path,
+    module: &Module,
+    resolve: &ResolveImport<'_>,
+) -> ImportedExports {
+    let mut imported = ImportedExports::new();
+    for stmt in &module.statements {
+        let Stmt::Import { path: import, .. } = stmt else {
+            continue;
+        };
+        let Some((file, version)) = resolve(path, import) else {
+            continue;
+        };
+        let Ok(source) = fs::read_to_string(file) else {
+            continue;
+        };
+        if let Ok(module) = Parser::with_version(version).parse(&source) {
+            imported.insert(import.clone(), exports(&module));
+        }
+    }
+    imported
+}
+
+/// Result of building a package
+#[derive(Debug, Default)]
+pub struct Build {
+    /// Diagnostics of all modules, already rendered and prefixed with the module path
+    pub messages: Vec<String>,
+    /// Whether every module was compiled
+    pub success: bool,
+    /// Compiled entry module
+    pub entry: PathBuf,
+}
+
+/// Compiles every module of the package and its dependencies with `profile`
+pub fn build(package: &Package, profile: &str) -> Result<Build, String> {
+    let Some(profile) = package.manifest.profiles.get(profile) else {
+        return Err(format!("unknown profile `{}`", profile));
+    };
+    let out_dir = package.root.join(&profile.out_dir);
+
+    let mut modules = Vec::new();
+    collect_package_modules(package, &mut modules, &mut HashSet::new())?;
+
+    let mut result = Build {
+        success: true,
+        ..Build::default()
+    };
+    let resolve = |from: &Path, import: &str| {
+        let file = package.owner(from).resolve_import(from, import)?;
+        let version = package.owner(&file).manifest.language_version;
+        Some((file, version))
+    };
+    for path in &modules {
+        let source =
+            fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let parser = Parser::with_version(package.owner(path).manifest.language_version);
+        let (mut module, syntax_errors) = parser.parse_recovering(&source);
+        let mut has_errors = false;
+        for diagnostic in syntax_errors {
+            has_errors |= diagnostic.severity == Severity::Error;
+            if diagnostic.severity == Severity::Error || profile.deny_warnings {
+                result.success = false;
+            }
+            result.messages.push(format!(
+                "{}: {}",
+                path.display(),
+                diagnostic.render(&source)
+            ));
+        }
+        if has_errors {
+            continue;
+        }
+
+        let imported = imported_consts(path, &module, &resolve, &mut Vec::new());
+        let exports = imported_exports(path, &module, &resolve);
+        for diagnostic in analyze(&module, &imported, &exports) {
+            if diagnostic.severity == Severity::Error || profile.deny_warnings {
+                result.success = false;
+            }
+            result.messages.push(format!(
+                "{}: {}",
+                path.display(),
+                diagnostic.render(&source)
+            ));
+        }
+        truncate_integer_division(&mut module, &imported);
+
+        let out = package
+            .output_path(&out_dir, path)
+            .ok_or_else(|| format!("{}: not in a source directory", path.display()))?;
+        for stmt in &mut module.statements {
+            if let Stmt::Import { path: import, .. } = stmt {
+                let target =
+                    resolve(path, import).and_then(|(t, _)| package.output_path(&out_dir, &t));
+                if let (Some(target), Some(dir)) = (target, out.parent()) {
+                    *import = relative_import(dir, &target);
+                }
+            }
+        }
+
+        if result.success {
+            if let Some(dir) = out.parent() {
+                fs::create_dir_all(dir).map_err(|err| format!("{}: {}", dir.display(), err))?;
+            }
+            fs::write(&out, gen(module)).map_err(|err| format!("{}: {}", out.display(), err))?;
+        }
+    }
+
+    let entry = package.root.join(&package.manifest.entry);
+    result.entry = package
+        .output_path(&out_dir, &normalize(&entry))
+        .ok_or_else(|| format!("entry {} is not in a source directory", entry.display()))?;
+    Ok(result)
+}
+
+// Modules of the package and all of its dependencies. A package shared by
+// several dependencies contributes its modules once, compared by their
+// canonical paths in `seen`.
+fn collect_package_modules(
+    package: &Package,
+    modules: &mut Vec<PathBuf>,
+    seen: &mut HashSet<PathBuf>,
+) -> Result<(), String> {
+    for dep in package.dependencies.values() {
+        collect_package_modules(dep, modules, seen)?;
+    }
+    let own = package
+        .modules()
+        .map_err(|err| format!("{}: {}", package.root.display(), err))?;
+    for path in own {
+        let canonical =
+            fs::canonicalize(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        if seen.insert(canonical) {
+            modules.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        env, fs,
+        path::{Path, PathBuf},
+        process,
+    };
+
+    use super::{
+        build, collect_package_modules, imported_consts, normalize, relative_import,
+        single_file_import, Package,
+    };
+    use crate::{
+        analyzer::consts::ConstValue,
+        parser::{parse, LanguageVersion},
+    };
+
+    // Writes `files` into a new directory inside of the system temp directory
+    fn temp_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = env::temp_dir().join(format!("sky-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (path, contents) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    fn workspace(name: &str) -> PathBuf {
+        temp_dir(
+            name,
+            &[
+                (
+                    "app/sky.toml",
+                    "[package]\nname = \"app\"\n\n[dependencies]\nutils = { path = \"../utils\" }",
+                ),
+                (
+                    "app/src/main.sky",
+                    "import utils::math::{LIMIT, twice}\nimport greet as g\nconst HALF = LIMIT / 2\ng.greet(twice(HALF))",
+                ),
+                ("app/src/greet.sky", "fn greet(n: i32): i32 = n"),
+                ("utils/sky.toml", "[package]\nname = \"utils\""),
+                (
+                    "utils/src/math.sky",
+                    "const LIMIT = 7\nfn twice(x: i32): i32 = x * 2",
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn loads_dependencies() {
+        let dir = workspace("load");
+        let package = Package::load(&dir.join("app")).unwrap();
+        assert_eq!(package.manifest.name, "app");
+        assert_eq!(package.root, fs::canonicalize(dir.join("app")).unwrap());
+        let utils = &package.dependencies["utils"];
+        assert_eq!(utils.manifest.name, "utils");
+        assert_eq!(utils.root, fs::canonicalize(dir.join("utils")).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_dependency_cycles() {
+        let dir = temp_dir(
+            "cycle",
+            &[
+                (
+                    "a/sky.toml",
+                    "[package]\nname = \"a\"\n\n[dependencies]\nb = { path = \"../b\" }",
+                ),
+                (
+                    "b/sky.toml",
+                    "[package]\nname = \"b\"\n\n[dependencies]\na = { path = \"../a\" }",
+                ),
+            ],
+        );
+        let err = Package::load(&dir.join("a")).unwrap_err();
+        assert!(
+            err.ends_with("sky.toml: package `a` depends on itself"),
+            "{}",
+            err
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn discovers_manifests() {
+        let dir = workspace("discover");
+        let app = dir.join("app");
+        assert_eq!(
+            Package::discover(&app.join("src/nested")),
+            Some(app.clone())
+        );
+        assert_eq!(Package::discover(&app), Some(app));
+        assert_eq!(Package::discover(&dir), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn resolves_imports_and_output_paths() {
+        let dir = workspace("resolve");
+        let package = Package::load(&dir.join("app")).unwrap();
+        let main = package.root.join("src/main.sky");
+        let math = package.dependencies["utils"].root.join("src/math.sky");
+        assert_eq!(
+            package.resolve_import(&main, "./greet.sky"),
+            Some(package.root.join("src/greet.sky"))
+        );
+        assert_eq!(
+            package.resolve_import(&main, "../lib/../src/greet.sky"),
+            Some(package.root.join("src/greet.sky"))
+        );
+        assert_eq!(
+            package.resolve_import(&main, "utils/math.sky"),
+            Some(math.clone())
+        );
+        assert_eq!(
+            package.resolve_import(&main, "greet.sky"),
+            Some(package.root.join("src/greet.sky"))
+        );
+        assert_eq!(
+            package.resolve_import(&main, "lib/math.sky"),
+            Some(package.root.join("src/lib/math.sky"))
+        );
+
+        assert_eq!(package.owner(&main).manifest.name, "app");
+        assert_eq!(package.owner(&math).manifest.name, "utils");
+        let out = package.root.join("build/dev");
+        assert_eq!(package.output_path(&out, &main), Some(out.join("main.mjs")));
+        assert_eq!(
+            package.output_path(&out, &math),
+            Some(out.join("deps/utils/math.mjs"))
+        );
+        assert_eq!(
+            package.output_path(&out, &package.root.join("other/a.sky")),
+            None
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn imported_modules_are_parsed_with_their_version() {
+        let dir = temp_dir("version", &[("consts.sky", "const A = 2\n(3)")]);
+        let main = dir.join("main.sky");
+        let module = parse("import { A } from \"./consts.sky\"").unwrap();
+        let consts = |version| {
+            let resolve = |from: &Path, import: &str| Some((from.parent()?.join(import), version));
+            imported_consts(&main, &module, &resolve, &mut Vec::new())["./consts.sky"].clone()
+        };
+        // Before version 2 the parenthesis on the next line calls the value
+        assert_eq!(consts(LanguageVersion::V1).get("A"), None);
+        assert_eq!(
+            consts(LanguageVersion::V2).get("A"),
+            Some(&ConstValue::Integer(2))
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn builds_packages() {
+        let dir = workspace("build");
+        let package = Package::load(&dir.join("app")).unwrap();
+        let result = build(&package, "dev").unwrap();
+        assert!(result.messages.is_empty(), "{:?}", result.messages);
+        assert!(result.success);
+        let out = package.root.join("build/dev");
+        assert_eq!(result.entry, out.join("main.mjs"));
+        assert_eq!(
+            fs::read_to_string(out.join("main.mjs")).unwrap(),
+            [
+                "import { LIMIT, twice } from \"./deps/utils/math.mjs\";",
+                "import * as g from \"./greet.mjs\";",
+                "export const HALF = (LIMIT / 2 | 0);",
+                "g.greet(twice(HALF));",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            fs::read_to_string(out.join("deps/utils/math.mjs")).unwrap(),
+            "export const LIMIT = 7;\nexport function twice(x) {\n  return x * 2;\n}\n"
+        );
+        assert!(build(&package, "bench").is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn builds_shared_dependencies_once() {
+        let dir = temp_dir(
+            "diamond",
+            &[
+                (
+                    "app/sky.toml",
+                    "[package]\nname = \"app\"\n\n[dependencies]\nleft = { path = \"../left\" }\nright = { path = \"../right\" }",
+                ),
+                ("app/src/main.sky", "import left::a::{A}\nimport right::b::{B}"),
+                (
+                    "left/sky.toml",
+                    "[package]\nname = \"left\"\n\n[dependencies]\nbase = { path = \"../base\" }",
+                ),
+                ("left/src/a.sky", "import base::c::{C}\nconst A = C"),
+                (
+                    "right/sky.toml",
+                    "[package]\nname = \"right\"\n\n[dependencies]\nbase = { path = \"../base\" }",
+                ),
+                ("right/src/b.sky", "import base::c::{C}\nconst B = C"),
+                ("base/sky.toml", "[package]\nname = \"base\""),
+                ("base/src/c.sky", "const C = 1"),
+            ],
+        );
+        let package = Package::load(&dir.join("app")).unwrap();
+        let mut modules = Vec::new();
+        collect_package_modules(&package, &mut modules, &mut HashSet::new()).unwrap();
+        let shared = fs::canonicalize(dir.join("base/src/c.sky")).unwrap();
+        assert_eq!(modules.iter().filter(|m| **m == shared).count(), 1);
+        assert_eq!(modules.len(), 4);
+        let result = build(&package, "dev").unwrap();
+        assert!(result.success, "{:?}", result.messages);
+        let out = package.root.join("build/dev/deps");
+        assert_eq!(
+            fs::read_to_string(out.join("right/b.mjs")).unwrap(),
+            "import { C } from \"../left/deps/base/c.mjs\";\nexport const B = C;\n"
+        );
+        assert!(out.join("left/deps/base/c.mjs").exists());
+        assert!(!out.join("right/deps").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn relative_imports() {
+        let out = Path::new("/app/build/dev");
+        assert_eq!(relative_import(out, &out.join("util.mjs")), "./util.mjs");
+        assert_eq!(
+            relative_import(&out.join("a"), &out.join("deps/utils/str.mjs")),
+            "../deps/utils/str.mjs"
+        );
+    }
+
+    #[test]
+    fn single_file_imports() {
+        let from = Path::new("/app/main.sky");
+        assert_eq!(single_file_import(from, "math/trig.sky"), "./math/trig.mjs");
+        assert_eq!(single_file_import(from, "./util.sky"), "./util.mjs");
+        assert_eq!(single_file_import(from, "../lib/str.sky"), "../lib/str.mjs");
+    }
+
+    #[test]
+    fn normalizes_paths() {
+        assert_eq!(
+            normalize(Path::new("/app/src/./a/../b.sky")),
+            Path::new("/app/src/b.sky")
+        );
+    }
+}
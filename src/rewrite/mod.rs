@@ -0,0 +1,190 @@
+//! Applies span based text edits, such as lint suggestions or quick-fixes,
+//! to the original source.
+
+use std::{error::Error, fmt, ops::Range};
+
+use peg::{error::ParseError, str::LineCol};
+
+use crate::parser::Parser;
+
+/// Replacement of the bytes in `range` with `text`.
+/// An empty range inserts `text` at its start.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn replace(range: Range<usize>, text: &str) -> Self {
+        Self {
+            range,
+            text: text.to_string(),
+        }
+    }
+
+    pub fn insert(offset: usize, text: &str) -> Self {
+        Self::replace(offset..offset, text)
+    }
+
+    pub fn delete(range: Range<usize>) -> Self {
+        Self::replace(range, "")
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RewriteError {
+    /// The range is reversed, past the end of the source
+    /// or splits a UTF-8 character
+    InvalidRange(Range<usize>),
+    /// Two edits touch the same bytes
+    Overlap(Range<usize>, Range<usize>),
+    /// The rewritten source doesn't parse anymore
+    Reparse(ParseError<LineCol>),
+}
+
+impl fmt::Display for RewriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RewriteError::InvalidRange(r) => write!(f, "invalid edit range {:?}", r),
+            RewriteError::Overlap(a, b) => write!(f, "edits {:?} and {:?} overlap", a, b),
+            RewriteError::Reparse(err) => write!(f, "rewritten source is invalid: {}", err),
+        }
+    }
+}
+
+impl Error for RewriteError {}
+
+/// Applies `edits` to `source` without checking the result.
+///
+/// Edits may come in any order, offsets always refer to the original source.
+/// Insertions at the same offset are applied in the order they were given.
+pub fn apply_edits(source: &str, mut edits: Vec<TextEdit>) -> Result<String, RewriteError> {
+    for edit in &edits {
+        let Range { start, end } = edit.range;
+        if start > end
+            || end > source.len()
+            || !source.is_char_boundary(start)
+            || !source.is_char_boundary(end)
+        {
+            return Err(RewriteError::InvalidRange(edit.range.clone()));
+        }
+    }
+
+    // Stable, so insertions at the same offset keep their order
+    edits.sort_by_key(|e| (e.range.start, e.range.end));
+    for pair in edits.windows(2) {
+        if pair[0].range.end > pair[1].range.start {
+            return Err(RewriteError::Overlap(
+                pair[0].range.clone(),
+                pair[1].range.clone(),
+            ));
+        }
+    }
+
+    let mut buf = String::with_capacity(source.len());
+    let mut pos = 0;
+    for edit in edits {
+        buf.push_str(&source[pos..edit.range.start]);
+        buf.push_str(edit.text.as_str());
+        pos = edit.range.end;
+    }
+    buf.push_str(&source[pos..]);
+    Ok(buf)
+}
+
+/// Applies `edits` to `source` and makes sure the result still parses
+/// with `parser`, which should be the one the source was parsed with.
+pub fn rewrite(
+    parser: &Parser,
+    source: &str,
+    edits: Vec<TextEdit>,
+) -> Result<String, RewriteError> {
+    let rewritten = apply_edits(source, edits)?;
+    parser.parse(&rewritten).map_err(RewriteError::Reparse)?;
+    Ok(rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_edits, rewrite, RewriteError, TextEdit};
+    use crate::parser::{LanguageVersion, Parser};
+
+    static STRICT: Parser = Parser::new();
+
+    #[test]
+    fn applies_edits_in_source_order() {
+        assert_eq!(
+            rewrite(
+                &STRICT,
+                "let a = 1\nlet b = a",
+                vec![
+                    TextEdit::replace(18..19, "c"),
+                    TextEdit::insert(4, "mut "),
+                    TextEdit::replace(4..5, "c"),
+                    TextEdit::insert(9, ";"),
+                ]
+            ),
+            Ok("let mut c = 1;\nlet b = c".to_string())
+        );
+    }
+
+    #[test]
+    fn insertions_keep_their_order() {
+        assert_eq!(
+            apply_edits(
+                "f()",
+                vec![TextEdit::insert(2, "a"), TextEdit::insert(2, ", b")]
+            ),
+            Ok("f(a, b)".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_overlapping_edits() {
+        assert_eq!(
+            apply_edits(
+                "let a = 1",
+                vec![TextEdit::delete(0..5), TextEdit::insert(4, "mut ")]
+            ),
+            Err(RewriteError::Overlap(0..5, 4..4))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        assert_eq!(
+            apply_edits("let a = 1", vec![TextEdit::delete(5..20)]),
+            Err(RewriteError::InvalidRange(5..20))
+        );
+        assert_eq!(
+            apply_edits("\"ё\"", vec![TextEdit::insert(2, "x")]),
+            Err(RewriteError::InvalidRange(2..2))
+        );
+    }
+
+    #[test]
+    fn rejects_unparsable_result() {
+        assert!(matches!(
+            rewrite(&STRICT, "let a = 1", vec![TextEdit::delete(8..9)]),
+            Err(RewriteError::Reparse(_))
+        ));
+    }
+
+    #[test]
+    fn reparses_with_the_given_version() {
+        let edits = vec![TextEdit::insert(9, " let b = a")];
+        assert_eq!(
+            rewrite(
+                &Parser::with_version(LanguageVersion::V1),
+                "let a = 1",
+                edits.clone()
+            ),
+            Ok("let a = 1 let b = a".to_string())
+        );
+        assert!(matches!(
+            rewrite(&STRICT, "let a = 1", edits),
+            Err(RewriteError::Reparse(_))
+        ));
+    }
+}
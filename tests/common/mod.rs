@@ -0,0 +1,30 @@
+//! Snapshot files which tests compare their output with.
+//!
+//! Run the tests with `SKY_UPDATE_SNAPSHOTS=1` to write new or changed
+//! snapshots instead of failing.
+
+use std::{env, fs, path::Path};
+
+/// Compares `actual` with the snapshot at `path`, the error describes
+/// the difference. Writes the snapshot instead when updating them.
+pub fn compare(path: &Path, actual: &str) -> Result<(), String> {
+    if env::var_os("SKY_UPDATE_SNAPSHOTS").is_some() {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| format!("{}: {}", dir.display(), err))?;
+        }
+        return fs::write(path, actual).map_err(|err| format!("{}: {}", path.display(), err));
+    }
+    match fs::read_to_string(path) {
+        Ok(expected) if expected == actual => Ok(()),
+        Ok(expected) => Err(format!(
+            "{} does not match\n--- expected\n{}--- actual\n{}",
+            path.display(),
+            expected,
+            actual
+        )),
+        Err(_) => Err(format!(
+            "missing {}, run with SKY_UPDATE_SNAPSHOTS=1 to create it",
+            path.display()
+        )),
+    }
+}
@@ -0,0 +1,52 @@
+//! Parser tests written as data.
+//!
+//! Every `corpus/<name>.sky` file is parsed and compared with the AST dump
+//! in `corpus/<name>.ast`. Syntax errors are listed after the dump, so files
+//! with mistakes in them test error recovery. Run the tests with
+//! `SKY_UPDATE_SNAPSHOTS=1` to write new or changed `.ast` files.
+
+use std::{fs, path::PathBuf};
+
+use sky::parser::Parser;
+
+mod common;
+
+// The AST dump followed by the syntax errors
+fn render_output(source: &str) -> String {
+    let (module, diagnostics) = Parser::new().parse_recovering(source);
+    let mut out = module.dump();
+    for diagnostic in diagnostics {
+        out.push_str(&diagnostic.render(source));
+        out.push('\n');
+    }
+    out
+}
+
+#[test]
+fn corpus() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+
+    let mut cases = fs::read_dir(&dir)
+        .expect("the corpus directory should exist")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "sky"))
+        .collect::<Vec<_>>();
+    cases.sort();
+    assert!(!cases.is_empty(), "the corpus has no .sky files");
+
+    let mut failures = Vec::new();
+    for case in &cases {
+        let source = fs::read_to_string(case).unwrap();
+        let actual = render_output(&source);
+        if let Err(err) = common::compare(&case.with_extension("ast"), &actual) {
+            failures.push(err);
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} of {} corpus files failed\n\n{}",
+        failures.len(),
+        cases.len(),
+        failures.join("\n")
+    );
+}